@@ -0,0 +1,59 @@
+//! Compares the render backends on a large mesh: `cargo bench --bench render`.
+
+use std::time::{Duration, Instant};
+
+use resvg::usvg;
+use triangulation::delaunay::Point;
+use triangulation::drawer::{Drawing, Fit, Paint, RenderBackend, Shape, Stroke};
+
+
+const WIDTH: f64 = 2000.0;
+const HEIGHT: f64 = 1500.0;
+const ITERATIONS: u32 = 5;
+
+/// Builds a grid of `columns` x `rows` cells, each split into two triangles.
+fn mesh(columns: usize, rows: usize, stroke: bool) -> Drawing {
+    let (cell_width, cell_height) = (WIDTH / columns as f64, HEIGHT / rows as f64);
+    let mut shapes = Vec::with_capacity(columns * rows * 2);
+
+    for column in 0..columns {
+        for row in 0..rows {
+            let point = |dx: usize, dy: usize| Point { x: (column + dx) as f64 * cell_width, y: (row + dy) as f64 * cell_height };
+            let color = usvg::Color::new_rgb((column * 7 % 256) as u8, (row * 5 % 256) as u8, 128);
+            let fill = Some(Paint { color, opacity: 1.0 });
+            let stroke = stroke.then_some(Stroke { paint: Paint { color: usvg::Color::black(), opacity: 1.0 }, width: 0.5 });
+
+            shapes.push(Shape { vertex: [point(0, 0), point(1, 0), point(1, 1)], fill, stroke });
+            shapes.push(Shape { vertex: [point(0, 0), point(1, 1), point(0, 1)], fill, stroke });
+        }
+    }
+
+    let background = Some(Paint { color: usvg::Color::white(), opacity: 1.0 });
+    Drawing::new(WIDTH, HEIGHT, background, shapes)
+}
+
+fn measure(drawing: &Drawing) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        drawing.render_with(Fit::Original).expect("the mesh is rendered");
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    for (columns, rows) in [(40, 25), (160, 160)] {
+        for stroke in [false, true] {
+            let mut timings = vec![];
+            for backend in [RenderBackend::Svg, RenderBackend::Raster] {
+                let drawing = mesh(columns, rows, stroke).with_backend(backend);
+                timings.push((backend, measure(&drawing)));
+            }
+
+            let triangles = columns * rows * 2;
+            for (backend, timing) in &timings {
+                println!("{:>6} triangles, stroke {:<5} {:<6} {:>8.1} ms", triangles, stroke, backend, timing.as_secs_f64() * 1000.0);
+            }
+            println!("{:>6} triangles, stroke {:<5} speedup {:.2}x", triangles, stroke, timings[0].1.as_secs_f64() / timings[1].1.as_secs_f64());
+        }
+    }
+}
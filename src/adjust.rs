@@ -0,0 +1,228 @@
+use image::Rgba;
+use serde::{Deserialize, Serialize};
+
+use crate::color::{hsl_to_rgb, rgb_to_hsl, Color};
+use crate::error::{Error, Result};
+
+
+/// Adjustments applied to the colors sampled for the triangles, in the order of the fields.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorAdjust {
+    /// added to every channel, between -1 (black) and 1 (white)
+    pub brightness: f64,
+    /// factor of the distance to the mid gray, 0 gives a flat gray and 1 keeps the colors
+    pub contrast: f64,
+    /// factor of the distance to the gray of same luminance, 0 gives a gray image and 1 keeps the colors
+    pub saturation: f64,
+    /// rotation of the hue, in degrees
+    pub hue: f64,
+    /// blend with the sepia tone, between 0 (none) and 1 (full sepia)
+    pub sepia: f64,
+    /// maps the luminance between two colors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duotone: Option<Duotone>,
+    /// number of levels kept per channel
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub posterize: Option<u8>,
+}
+
+/// Colors of the dark and light ends of a duotone mapping.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Duotone {
+    pub shadows: Color,
+    pub highlights: Color,
+}
+
+impl Default for ColorAdjust {
+
+    fn default() -> Self {
+        Self {
+            brightness: 0.0,
+            contrast: 1.0,
+            saturation: 1.0,
+            hue: 0.0,
+            sepia: 0.0,
+            duotone: None,
+            posterize: None,
+        }
+    }
+}
+
+/// Relative luminance of a color, with the Rec. 601 weights.
+fn luma([red, green, blue]: [f64; 3]) -> f64 {
+    0.299 * red + 0.587 * green + 0.114 * blue
+}
+
+fn mix(from: f64, to: f64, amount: f64) -> f64 {
+    from + (to - from) * amount
+}
+
+impl ColorAdjust {
+
+    /// Returns whenever the adjustment leaves every color unchanged.
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Checks that every parameter is in its valid range.
+    pub fn validate(&self) -> Result<()> {
+        if !(-1.0..=1.0).contains(&self.brightness) {
+            return Err(Error::invalid_parameter(
+                "brightness",
+                format!("must be between -1 and 1, got {}", self.brightness),
+            ));
+        }
+        if !(self.contrast.is_finite() && self.contrast >= 0.0) {
+            return Err(Error::invalid_parameter(
+                "contrast",
+                format!("must be a positive number, got {}", self.contrast),
+            ));
+        }
+        if !(self.saturation.is_finite() && self.saturation >= 0.0) {
+            return Err(Error::invalid_parameter(
+                "saturation",
+                format!("must be a positive number, got {}", self.saturation),
+            ));
+        }
+        if !self.hue.is_finite() {
+            return Err(Error::invalid_parameter("hue", format!("must be a number of degrees, got {}", self.hue)));
+        }
+        if !(0.0..=1.0).contains(&self.sepia) {
+            return Err(Error::invalid_parameter(
+                "sepia",
+                format!("must be between 0 and 1, got {}", self.sepia),
+            ));
+        }
+        if matches!(self.posterize, Some(levels) if levels < 2) {
+            return Err(Error::invalid_parameter("posterize", "must keep at least 2 levels"));
+        }
+        Ok(())
+    }
+
+    /// Adjusts the color of `pixel`, keeping its alpha.
+    pub fn apply(&self, pixel: Rgba<u8>) -> Rgba<u8> {
+        if self.is_identity() {
+            return pixel;
+        }
+
+        let mut rgb = [pixel[0], pixel[1], pixel[2]].map(|channel| channel as f64 / 255.0);
+
+        if self.brightness != 0.0 {
+            rgb = rgb.map(|channel| channel + self.brightness);
+        }
+        if self.contrast != 1.0 {
+            rgb = rgb.map(|channel| (channel - 0.5) * self.contrast + 0.5);
+        }
+        rgb = rgb.map(|channel| channel.clamp(0.0, 1.0));
+
+        if self.saturation != 1.0 {
+            let gray = luma(rgb);
+            rgb = rgb.map(|channel| mix(gray, channel, self.saturation).clamp(0.0, 1.0));
+        }
+        if self.hue.rem_euclid(360.0) != 0.0 {
+            let (hue, saturation, lightness) = rgb_to_hsl(rgb[0], rgb[1], rgb[2]);
+            let (red, green, blue) = hsl_to_rgb(hue + self.hue, saturation, lightness);
+            rgb = [red, green, blue];
+        }
+        if self.sepia > 0.0 {
+            let [red, green, blue] = rgb;
+            let sepia = [
+                0.393 * red + 0.769 * green + 0.189 * blue,
+                0.349 * red + 0.686 * green + 0.168 * blue,
+                0.272 * red + 0.534 * green + 0.131 * blue,
+            ];
+            rgb = [0, 1, 2].map(|i| mix(rgb[i], sepia[i].min(1.0), self.sepia));
+        }
+        if let Some(duotone) = &self.duotone {
+            let gray = luma(rgb);
+            let shadows = [duotone.shadows.red, duotone.shadows.green, duotone.shadows.blue];
+            let highlights = [duotone.highlights.red, duotone.highlights.green, duotone.highlights.blue];
+            rgb = [0, 1, 2].map(|i| mix(shadows[i] as f64, highlights[i] as f64, gray) / 255.0);
+        }
+        if let Some(levels) = self.posterize.filter(|levels| *levels >= 2) {
+            let steps = (levels - 1) as f64;
+            rgb = rgb.map(|channel| (channel * steps).round() / steps);
+        }
+
+        let [red, green, blue] = rgb.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8);
+        Rgba::from([red, green, blue, pixel[3]])
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use image::Rgba;
+
+    use super::{ColorAdjust, Duotone};
+    use crate::color::Color;
+    use crate::error::test::assert_invalid;
+
+    fn adjust(adjust: ColorAdjust, rgb: [u8; 3]) -> [u8; 3] {
+        let pixel = adjust.apply(Rgba::from([rgb[0], rgb[1], rgb[2], 77]));
+        assert_eq!(pixel[3], 77);
+        [pixel[0], pixel[1], pixel[2]]
+    }
+
+    #[test]
+    fn test_identity() {
+        let identity = ColorAdjust::default();
+        assert!(identity.is_identity());
+        assert_eq!(adjust(identity, [12, 200, 99]), [12, 200, 99]);
+        assert_eq!(adjust(ColorAdjust { hue: 360.0, ..Default::default() }, [12, 200, 99]), [12, 200, 99]);
+    }
+
+    #[test]
+    fn test_brightness_and_contrast() {
+        assert_eq!(adjust(ColorAdjust { brightness: 0.2, ..Default::default() }, [0, 100, 250]), [51, 151, 255]);
+        assert_eq!(adjust(ColorAdjust { brightness: -1.0, ..Default::default() }, [0, 100, 250]), [0, 0, 0]);
+        assert_eq!(adjust(ColorAdjust { contrast: 0.0, ..Default::default() }, [0, 100, 250]), [128, 128, 128]);
+        assert_eq!(adjust(ColorAdjust { contrast: 2.0, ..Default::default() }, [64, 128, 200]), [0, 129, 255]);
+    }
+
+    #[test]
+    fn test_saturation_and_hue() {
+        let gray = adjust(ColorAdjust { saturation: 0.0, ..Default::default() }, [255, 0, 0]);
+        assert_eq!(gray, [76, 76, 76]);
+        assert_eq!(adjust(ColorAdjust { saturation: 2.0, ..Default::default() }, [128, 128, 128]), [128, 128, 128]);
+
+        assert_eq!(adjust(ColorAdjust { hue: 120.0, ..Default::default() }, [255, 0, 0]), [0, 255, 0]);
+        assert_eq!(adjust(ColorAdjust { hue: -120.0, ..Default::default() }, [255, 0, 0]), [0, 0, 255]);
+    }
+
+    #[test]
+    fn test_sepia_duotone_posterize() {
+        assert_eq!(adjust(ColorAdjust { sepia: 1.0, ..Default::default() }, [100, 100, 100]), [135, 120, 94]);
+        assert_eq!(adjust(ColorAdjust { sepia: 1.0, ..Default::default() }, [255, 255, 255]), [255, 255, 239]);
+
+        let duotone = Duotone { shadows: Color::new_rgb(0, 0, 100), highlights: Color::new_rgb(255, 200, 0) };
+        let duotone = ColorAdjust { duotone: Some(duotone), ..Default::default() };
+        assert_eq!(adjust(duotone.clone(), [0, 0, 0]), [0, 0, 100]);
+        assert_eq!(adjust(duotone.clone(), [255, 255, 255]), [255, 200, 0]);
+        assert_eq!(adjust(duotone, [128, 128, 128]), [128, 100, 50]);
+
+        let posterize = ColorAdjust { posterize: Some(2), ..Default::default() };
+        assert_eq!(adjust(posterize, [10, 120, 200]), [0, 0, 255]);
+        let posterize = ColorAdjust { posterize: Some(3), ..Default::default() };
+        assert_eq!(adjust(posterize, [10, 120, 200]), [0, 128, 255]);
+    }
+
+    #[test]
+    fn test_validate() {
+        let test_cases = vec![
+            ("brightness", ColorAdjust { brightness: 1.5, ..Default::default() }),
+            ("contrast", ColorAdjust { contrast: -1.0, ..Default::default() }),
+            ("saturation", ColorAdjust { saturation: f64::NAN, ..Default::default() }),
+            ("hue", ColorAdjust { hue: f64::INFINITY, ..Default::default() }),
+            ("sepia", ColorAdjust { sepia: 2.0, ..Default::default() }),
+            ("posterize", ColorAdjust { posterize: Some(1), ..Default::default() }),
+        ];
+
+        for (expected, adjust) in test_cases {
+            assert_invalid(adjust.validate(), expected);
+        }
+        assert!(ColorAdjust::default().validate().is_ok());
+    }
+}
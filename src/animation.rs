@@ -0,0 +1,353 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, ImageFormat, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::delaunay::{Delaunay, Point, Triangle};
+use crate::drawer::{Drawer, Drawing, Fit};
+use crate::error::{Error, Result};
+use crate::Edges;
+
+
+/// Order in which an [`Animation`] builds the triangulation up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BuildOrder {
+    /// the triangles appear in the order the triangulation created them
+    #[default]
+    Insertion,
+    /// the triangles appear from the center of the image to its corners
+    Distance,
+    /// the largest triangles appear first, then the details
+    Size,
+    /// the points are added over time, each frame is the triangulation of the points added so far
+    Points,
+}
+
+impl fmt::Display for BuildOrder {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildOrder::Insertion => f.pad("insertion"),
+            BuildOrder::Distance => f.pad("distance"),
+            BuildOrder::Size => f.pad("size"),
+            BuildOrder::Points => f.pad("points"),
+        }
+    }
+}
+
+impl FromStr for BuildOrder {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "insertion" => Ok(BuildOrder::Insertion),
+            "distance" => Ok(BuildOrder::Distance),
+            "size" => Ok(BuildOrder::Size),
+            "points" => Ok(BuildOrder::Points),
+            _ => Err(format!("unknown build order `{}` (expected insertion, distance, size or points)", value)),
+        }
+    }
+}
+
+/// File format of an animation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationFormat {
+    Gif,
+    /// animated png, read as a still image (its last frame) by the viewers without apng support
+    Apng,
+}
+
+impl AnimationFormat {
+
+    /// Guess the format from the extension of `path` (`.gif`, `.png` or `.apng`).
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "apng" => Some(AnimationFormat::Apng),
+            _ => Self::from_image_format(ImageFormat::from_extension(extension)?),
+        }
+    }
+
+    pub fn from_image_format(format: ImageFormat) -> Option<Self> {
+        match format {
+            ImageFormat::Gif => Some(AnimationFormat::Gif),
+            ImageFormat::Png => Some(AnimationFormat::Apng),
+            _ => None,
+        }
+    }
+}
+
+/// Number and duration of the frames of an [`Animation`] or a [`Morph`](crate::morph::Morph).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timing {
+    /// number of frames
+    pub frames: usize,
+    /// duration of each frame, in milliseconds
+    pub delay: u16,
+    /// duration of the last frame, in milliseconds
+    pub hold: u16,
+}
+
+impl Default for Timing {
+
+    fn default() -> Self {
+        Self { frames: 30, delay: 80, hold: 1000 }
+    }
+}
+
+/// Maximum number of frames of a [`Timing`].
+pub const MAX_FRAMES: usize = 1000;
+
+impl Timing {
+
+    /// Checks that every parameter is in its valid range, with at least `min_frames` frames.
+    pub fn validate(&self, min_frames: usize) -> Result<()> {
+        if !(min_frames..=MAX_FRAMES).contains(&self.frames) {
+            return Err(Error::invalid_parameter(
+                "frames",
+                format!("must be between {} and {}, got {}", min_frames, MAX_FRAMES, self.frames),
+            ));
+        }
+        if self.delay == 0 {
+            return Err(Error::invalid_parameter("delay", "must be at least 1 millisecond"));
+        }
+        if self.hold == 0 {
+            return Err(Error::invalid_parameter("hold", "must be at least 1 millisecond"));
+        }
+        Ok(())
+    }
+}
+
+/// Animation of the triangulation building up, frame after frame.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Animation {
+    pub order: BuildOrder,
+    /// the last frame shows the whole triangulation
+    pub timing: Timing,
+}
+
+impl Animation {
+
+    /// Checks that every parameter is in its valid range.
+    pub fn validate(&self) -> Result<()> {
+        self.timing.validate(1)
+    }
+
+    /// Number of items shown by the frame `frame` (starting at 0) when `total` items are revealed.
+    fn revealed(&self, frame: usize, total: usize) -> usize {
+        (total * (frame + 1)).div_ceil(self.timing.frames)
+    }
+
+    /// Builds the drawing of each frame from the points sampled on `source_image`, the markers
+    /// follow `edges` as in [`Drawer::markers`].
+    pub fn drawings(&self, drawer: &Drawer, source_image: &RgbaImage, points: Vec<Point>, edges: Option<&Edges>) -> Result<Vec<Drawing>> {
+        self.validate()?;
+        let (width, height) = source_image.dimensions();
+        let mut delaunay = Delaunay::new(height as f64, width as f64);
+
+        if self.order == BuildOrder::Points {
+            let mut drawings = Vec::with_capacity(self.timing.frames);
+            let mut added = 0;
+            for frame in 0..self.timing.frames {
+                let count = self.revealed(frame, points.len());
+                delaunay.add_points(points[added..count].to_vec());
+                added = count;
+                drawings.push(drawer.draw_with_edges(source_image.clone(), delaunay.triangles(), edges)?);
+            }
+            return Ok(drawings);
+        }
+
+        delaunay.add_points(points);
+        let mut triangles = delaunay.triangles();
+        let distance = |triangle: &Triangle| {
+            let center = triangle.center();
+            (center.x - width as f64 / 2.0).powi(2) + (center.y - height as f64 / 2.0).powi(2)
+        };
+        match self.order {
+            BuildOrder::Distance => triangles.sort_by(|a, b| distance(a).total_cmp(&distance(b))),
+            BuildOrder::Size => triangles.sort_by(|a, b| b.area().total_cmp(&a.area())),
+            BuildOrder::Insertion | BuildOrder::Points => {}
+        }
+
+        // the triangles are drawn once, each frame shows the first shapes
+        let drawing = drawer.draw_with_edges(source_image.clone(), triangles, edges)?;
+        let (width, height) = drawing.size();
+        let shapes = drawing.shapes();
+        let drawings = (0..self.timing.frames)
+            .map(|frame| {
+                let shapes = shapes[..self.revealed(frame, shapes.len())].to_vec();
+                // the markers appear with the first triangle using their vertex
+                let vertices: HashSet<(u64, u64)> = shapes.iter()
+                    .flat_map(|shape| shape.vertex)
+                    .map(|point| (point.x.to_bits(), point.y.to_bits()))
+                    .collect();
+                let markers = drawing.markers().iter()
+                    .filter(|marker| vertices.contains(&(marker.center.x.to_bits(), marker.center.y.to_bits())))
+                    .copied()
+                    .collect();
+                Drawing::new(width, height, drawing.background(), shapes)
+                    .with_markers(markers)
+                    .with_backend(drawing.backend())
+                    .with_seamless(drawing.seamless())
+            })
+            .collect();
+        Ok(drawings)
+    }
+
+    /// Renders the frames and writes the animation to `writer`, looping forever.
+    pub fn encode<W: Write>(&self, drawings: &[Drawing], writer: W, fit: Fit, format: AnimationFormat) -> Result<()> {
+        self.validate()?;
+        encode_frames(drawings, writer, fit, format, self.timing)
+    }
+}
+
+/// Renders `drawings` and writes them as an animation looping forever, each frame lasting the
+/// `delay` of `timing` but the last one which lasts its `hold`.
+pub fn encode_frames<W: Write>(
+    drawings: &[Drawing],
+    writer: W,
+    fit: Fit,
+    format: AnimationFormat,
+    timing: Timing,
+) -> Result<()> {
+    let Some(first) = drawings.first() else {
+        return Err(Error::DegenerateGeometry("the animation has no frame".to_owned()));
+    };
+    let delay = |frame: usize| if frame + 1 == drawings.len() { timing.hold } else { timing.delay }.max(1);
+
+    match format {
+        AnimationFormat::Gif => {
+            let mut encoder = GifEncoder::new_with_speed(writer, 10);
+            encoder.set_repeat(Repeat::Infinite)?;
+            for (frame, drawing) in drawings.iter().enumerate() {
+                let delay = Delay::from_numer_denom_ms(delay(frame) as u32, 1);
+                encoder.encode_frame(Frame::from_parts(drawing.to_image_with(fit)?, 0, 0, delay))?;
+            }
+        }
+        AnimationFormat::Apng => {
+            let (width, height) = fit.fit_to(first.size().0, first.size().1)
+                .ok_or_else(|| Error::invalid_parameter("fit", format!("{:?} gives an empty image", fit)))?;
+            let mut encoder = png::Encoder::new(writer, width, height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.set_animated(drawings.len() as u32, 0).map_err(apng_error)?;
+            let mut writer = encoder.write_header().map_err(apng_error)?;
+            for (frame, drawing) in drawings.iter().enumerate() {
+                writer.set_frame_delay(delay(frame), 1000).map_err(apng_error)?;
+                writer.write_image_data(&drawing.to_image_with(fit)?).map_err(apng_error)?;
+            }
+            writer.finish().map_err(apng_error)?;
+        }
+    }
+    Ok(())
+}
+
+fn apng_error(error: png::EncodingError) -> Error {
+    match error {
+        png::EncodingError::IoError(error) => Error::Io(error),
+        error => Error::Encoding(Box::new(error)),
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use image::codecs::gif::GifDecoder;
+    use image::codecs::png::PngDecoder;
+    use image::{AnimationDecoder, Rgba, RgbaImage};
+
+    use super::{Animation, AnimationFormat, BuildOrder, Timing};
+    use crate::delaunay::{Delaunay, Point, Triangle};
+    use crate::drawer::{Drawer, Fit};
+    use crate::error::test::assert_invalid;
+
+    fn source() -> (RgbaImage, Vec<Point>) {
+        let image = RgbaImage::from_fn(40, 30, |x, y| Rgba::from([(x * 6) as u8, (y * 8) as u8, 100, 255]));
+        let points = (0..25).map(|i| Point { x: (i * 7 % 40) as f64 + 0.5, y: (i * 11 % 30) as f64 + 0.5 }).collect();
+        (image, points)
+    }
+
+    #[test]
+    fn test_build_order_from_str() {
+        assert_eq!("Size".parse(), Ok(BuildOrder::Size));
+        assert_eq!(BuildOrder::Points.to_string().parse(), Ok(BuildOrder::Points));
+        assert!("random".parse::<BuildOrder>().is_err());
+
+        assert_eq!(AnimationFormat::from_path("out.GIF"), Some(AnimationFormat::Gif));
+        assert_eq!(AnimationFormat::from_path("out.apng"), Some(AnimationFormat::Apng));
+        assert_eq!(AnimationFormat::from_path("out.png"), Some(AnimationFormat::Apng));
+        assert_eq!(AnimationFormat::from_path("out.jpeg"), None);
+    }
+
+    #[test]
+    fn test_drawings() {
+        let (image, points) = source();
+        let mut delaunay = Delaunay::new(30.0, 40.0);
+        delaunay.add_points(points.clone());
+        let total = delaunay.triangles().len();
+
+        for order in [BuildOrder::Insertion, BuildOrder::Distance, BuildOrder::Size, BuildOrder::Points] {
+            let animation = Animation { order, timing: Timing { frames: 6, ..Default::default() } };
+            let drawings = animation.drawings(&Drawer::default(), &image, points.clone(), None).unwrap();
+            assert_eq!(drawings.len(), 6, "{}", order);
+            assert_eq!(drawings.last().unwrap().shapes().len(), total, "{}", order);
+            if order != BuildOrder::Points {
+                let counts: Vec<usize> = drawings.iter().map(|drawing| drawing.shapes().len()).collect();
+                assert!(counts.windows(2).all(|pair| pair[0] < pair[1]), "{}: {:?}", order, counts);
+            }
+        }
+
+        // the largest triangles come first
+        let animation = Animation { order: BuildOrder::Size, timing: Timing { frames: 2, ..Default::default() } };
+        let drawings = animation.drawings(&Drawer::default(), &image, points, None).unwrap();
+        let areas: Vec<f64> = drawings[1].shapes().iter()
+            .map(|shape| Triangle::new(shape.vertex[0], shape.vertex[1], shape.vertex[2]).area())
+            .collect();
+        assert!(areas.windows(2).all(|pair| pair[0] >= pair[1]));
+    }
+
+    #[test]
+    fn test_encode() {
+        let (image, points) = source();
+        let animation = Animation { timing: Timing { frames: 4, delay: 50, hold: 500 }, ..Default::default() };
+        let drawings = animation.drawings(&Drawer::default(), &image, points, None).unwrap();
+
+        let mut gif = vec![];
+        animation.encode(&drawings, &mut gif, Fit::Width(80), AnimationFormat::Gif).unwrap();
+        let frames = GifDecoder::new(Cursor::new(gif)).unwrap().into_frames().collect_frames().unwrap();
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[0].buffer().dimensions(), (80, 60));
+        assert_eq!(frames[0].delay().numer_denom_ms(), (50, 1));
+        assert_eq!(frames[3].delay().numer_denom_ms(), (500, 1));
+
+        let mut apng = vec![];
+        animation.encode(&drawings, &mut apng, Fit::Original, AnimationFormat::Apng).unwrap();
+        let frames = PngDecoder::new(Cursor::new(apng)).unwrap().apng().into_frames().collect_frames().unwrap();
+        assert_eq!(frames.len(), 4);
+        // png is lossless, the last frame is the whole drawing
+        assert_eq!(frames[3].buffer(), &drawings[3].to_image_with(Fit::Original).unwrap());
+    }
+
+    #[test]
+    fn test_validate() {
+        let test_cases = vec![
+            ("frames", Animation { timing: Timing { frames: 0, ..Default::default() }, ..Default::default() }),
+            ("frames", Animation { timing: Timing { frames: 5000, ..Default::default() }, ..Default::default() }),
+            ("delay", Animation { timing: Timing { delay: 0, ..Default::default() }, ..Default::default() }),
+            ("hold", Animation { timing: Timing { hold: 0, ..Default::default() }, ..Default::default() }),
+        ];
+
+        for (expected, animation) in test_cases {
+            assert_invalid(animation.validate(), expected);
+        }
+        assert!(Animation::default().validate().is_ok());
+    }
+}
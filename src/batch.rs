@@ -0,0 +1,224 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use image::ImageFormat;
+
+use crate::error::{Error, Result};
+
+
+/// Extensions of the files picked up when a directory is given as input.
+pub const IMAGE_EXTENSIONS: [&str; 8] = ["png", "jpg", "jpeg", "bmp", "gif", "webp", "tif", "tiff"];
+
+/// Default template of the output file names, see [`output_path`].
+pub const DEFAULT_TEMPLATE: &str = "{stem}.png";
+
+/// Default template of the output file names of the images written as `format`.
+pub fn default_template(format: ImageFormat) -> String {
+    let extension = format.extensions_str().first().copied().unwrap_or("png");
+    format!("{{stem}}.{}", extension)
+}
+
+/// Checks that the extension of `template` is an extension of `format`. Templates without
+/// extension or whose extension is a placeholder are accepted.
+pub fn check_template(template: &str, format: ImageFormat) -> Result<()> {
+    let extension = Path::new(template).extension().and_then(|ext| ext.to_str());
+    match extension {
+        Some(ext) if !ext.contains('{') && ImageFormat::from_extension(ext) != Some(format) => Err(Error::invalid_parameter(
+            "template",
+            format!("the extension of `{}` does not match the {:?} format", template, format),
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| IMAGE_EXTENSIONS.iter().any(|image_ext| ext.eq_ignore_ascii_case(image_ext)))
+        .unwrap_or(false)
+}
+
+fn is_pattern(input: &str) -> bool {
+    input.contains(['*', '?', '['])
+}
+
+/// Expands the inputs into a list of image files.
+///
+/// Each input is either a file, a directory (every image directly inside it) or a glob pattern
+/// such as `photos/**/*.jpg`. Files listed twice are only kept once.
+pub fn collect_inputs(inputs: &[String]) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+
+    for input in inputs {
+        if is_pattern(input) {
+            let paths = glob::glob(input)
+                .map_err(|e| Error::invalid_parameter("input", format!("invalid pattern `{}`: {}", input, e)))?;
+            let mut matches = vec![];
+            for path in paths {
+                let path = path.map_err(|e| Error::Io(e.into()))?;
+                if path.is_file() && is_image(&path) {
+                    matches.push(path);
+                }
+            }
+            if matches.is_empty() {
+                return Err(Error::invalid_parameter("input", format!("no image matches `{}`", input)));
+            }
+            files.extend(matches);
+        } else {
+            let path = PathBuf::from(input);
+            if path.is_dir() {
+                let mut entries = vec![];
+                for entry in path.read_dir()? {
+                    let entry_path = entry?.path();
+                    if entry_path.is_file() && is_image(&entry_path) {
+                        entries.push(entry_path);
+                    }
+                }
+                entries.sort();
+                files.extend(entries);
+            } else if path.is_file() {
+                files.push(path);
+            } else {
+                return Err(Error::invalid_parameter("input", format!("`{}` does not exist", input)));
+            }
+        }
+    }
+
+    let mut seen = HashSet::new();
+    files.retain(|path| seen.insert(path.clone()));
+    Ok(files)
+}
+
+/// Builds the path of the output file of `input` in `directory`.
+///
+/// The template supports the placeholders `{stem}` (file name without extension), `{name}`
+/// (file name), `{ext}` (extension) and `{index}` (1-based position in the batch).
+pub fn output_path(directory: &Path, template: &str, input: &Path, index: usize) -> PathBuf {
+    let os_str = |value: Option<&std::ffi::OsStr>| value.map(|v| v.to_string_lossy().into_owned()).unwrap_or_default();
+    let file_name = template
+        .replace("{stem}", &os_str(input.file_stem()))
+        .replace("{name}", &os_str(input.file_name()))
+        .replace("{ext}", &os_str(input.extension()))
+        .replace("{index}", &(index + 1).to_string());
+    directory.join(file_name)
+}
+
+/// Builds the output paths of every input, failing if two inputs would write the same file.
+pub fn output_paths(directory: &Path, template: &str, inputs: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut seen = HashSet::new();
+    inputs.iter().enumerate()
+        .map(|(index, input)| {
+            let output = output_path(directory, template, input, index);
+            if seen.insert(output.clone()) {
+                Ok(output)
+            } else {
+                Err(Error::invalid_parameter(
+                    "template",
+                    format!("`{}` is generated for several inputs, add `{{index}}` to the template", output.display()),
+                ))
+            }
+        })
+        .collect()
+}
+
+/// Runs `task` on every item using up to `jobs` threads, the results keep the order of `items`.
+pub fn run_parallel<T, R, F>(items: &[T], jobs: usize, task: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new(items.iter().map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, items.len().max(1)) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(item) = items.get(index) else {
+                    break;
+                };
+                let result = task(item);
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+
+    results.into_inner().unwrap()
+        .into_iter()
+        .map(|result| result.expect("every item is processed"))
+        .collect()
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use image::ImageFormat;
+
+    use super::{check_template, collect_inputs, default_template, output_path, output_paths, run_parallel};
+    use crate::error::test::assert_invalid;
+
+    #[test]
+    fn test_output_path() {
+        let input = Path::new("photos/shoe.jpeg");
+        let directory = Path::new("out");
+
+        assert_eq!(output_path(directory, "{stem}.png", input, 0), PathBuf::from("out/shoe.png"));
+        assert_eq!(output_path(directory, "{index}_{name}.png", input, 4), PathBuf::from("out/5_shoe.jpeg.png"));
+        assert_eq!(output_path(directory, "{stem}-{ext}.png", input, 0), PathBuf::from("out/shoe-jpeg.png"));
+    }
+
+    #[test]
+    fn test_template_format() {
+        assert_eq!(default_template(ImageFormat::Jpeg), "{stem}.jpg");
+        assert_eq!(default_template(ImageFormat::Png), "{stem}.png");
+
+        for template in ["{stem}.jpeg", "{stem}.JPG", "{stem}", "{stem}.{ext}"] {
+            assert!(check_template(template, ImageFormat::Jpeg).is_ok(), "{}", template);
+        }
+        assert_invalid(check_template("{stem}.png", ImageFormat::Jpeg), "template");
+    }
+
+    #[test]
+    fn test_output_paths_collision() {
+        let inputs = vec![PathBuf::from("a/shoe.jpeg"), PathBuf::from("b/shoe.jpeg")];
+        assert!(output_paths(Path::new("out"), "{stem}.png", &inputs).is_err());
+        assert!(output_paths(Path::new("out"), "{stem}_{index}.png", &inputs).is_ok());
+    }
+
+    #[test]
+    fn test_collect_inputs() {
+        let directory = std::env::temp_dir().join("triangulation_collect_inputs");
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        for name in ["b.png", "a.JPG", "notes.txt"] {
+            fs::write(directory.join(name), b"").unwrap();
+        }
+        let directory_str = directory.to_string_lossy().into_owned();
+
+        let files = collect_inputs(std::slice::from_ref(&directory_str)).unwrap();
+        assert_eq!(files, vec![directory.join("a.JPG"), directory.join("b.png")]);
+
+        let files = collect_inputs(&[format!("{}/*.png", directory_str), directory_str.clone()]).unwrap();
+        assert_eq!(files, vec![directory.join("b.png"), directory.join("a.JPG")]);
+
+        assert!(collect_inputs(&[format!("{}/*.gif", directory_str)]).is_err());
+        assert!(collect_inputs(&[format!("{}/missing.png", directory_str)]).is_err());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_run_parallel() {
+        let items: Vec<usize> = (0..100).collect();
+        let results = run_parallel(&items, 8, |item| item * 2);
+        assert_eq!(results, items.iter().map(|item| item * 2).collect::<Vec<_>>());
+        assert!(run_parallel(&[] as &[usize], 4, |item| *item).is_empty());
+    }
+}
@@ -1,16 +1,41 @@
 
-use clap::Parser;
-use resvg::usvg;
-use image::io::Reader as ImageReader;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufWriter, Cursor, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::{parser::ValueSource, ArgAction, ArgGroup, ArgMatches, CommandFactory, FromArgMatches, Parser, ValueEnum};
+use image::{imageops, io::Reader as ImageReader, DynamicImage, ImageFormat, RgbaImage};
 use anyhow::{anyhow, Result, Context};
 use colored::Colorize;
+use serde::Serialize;
 use spinners::{Spinners, Spinner};
 
 
-use crate::drawer::{Drawable, Drawer}; 
+use crate::adjust::Duotone;
+use crate::animation::{self, Animation, AnimationFormat, BuildOrder, Timing};
+use crate::batch;
+use crate::color::Color;
+use crate::config::Config;
+use crate::drawer::{self, Drawing};
+use crate::debug::{self, Stage};
+use crate::delaunay::Triangle;
+use crate::mesh::{self, Mesh, MeshFormat};
+use crate::points::{PointSet, PointsMode};
+use crate::morph::Morph;
+use crate::palette::Palette;
+use crate::preset::Preset;
+use crate::sequence::{self, Sequence};
+use crate::stats::Stats;
+use crate::drawer::{Drawer, DrawerBuilder, Fit, RenderBackend, StrokeMode, VertexSizing}; 
+use crate::{Edges, Triangulation, TriangulationBuilder};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
+#[command(group = ArgGroup::new("animation").args(["animate", "morph"]))]
 struct Arguments {
     /// blur filter radius
     #[arg(long = "bf", default_value_t = 1)]
@@ -33,71 +58,386 @@ struct Arguments {
     point_rate: f64,
     
     /// convert image to grayscale 
-    #[arg(long = "gr", default_value_t = false)]
+    #[arg(long = "gr", default_value_t = false, overrides_with = "no_grayscale")]
     grayscale: bool,
+
+    /// keep the colors, overrides `grayscale = true` from --config or --preset
+    #[arg(long = "no-gr", default_value_t = false, overrides_with = "grayscale")]
+    no_grayscale: bool,
+
+    /// detect the points on a copy of the image downscaled to this size (largest side in pixel),
+    /// `bf` and `sf` are scaled down with it
+    #[arg(long = "work-size")]
+    work_size: Option<u32>,
+
+    /// seed of the random point sampling, the same image and seed always give the same result
+    #[arg(long = "seed")]
+    seed: Option<u64>,
     
-    /// source image
-    #[arg(long="in")]
-    input: String,
+    /// source image(s): files, directories or glob patterns (e.g. "photos/*.jpg"), "-" reads from stdin
+    #[arg(long="in", num_args = 1.., required_unless_present_any = ["dump_config", "list_presets"])]
+    input: Vec<String>,
     
-    /// destination image
-    #[arg(long="out")]
-    output: String,
+    /// destination image, "-" writes to stdout
+    #[arg(long="out", conflicts_with = "out_dir", required_unless_present_any = ["out_dir", "dump_config", "list_presets"])]
+    output: Option<String>,
+
+    /// destination directory when several images are processed
+    #[arg(long = "out-dir")]
+    out_dir: Option<String>,
+
+    /// file name of the generated images in --out-dir, supports {stem}, {name}, {ext} and {index} (default: {stem}.png, or the extension of --format)
+    #[arg(long = "name", default_value = batch::DEFAULT_TEMPLATE, requires = "out_dir")]
+    name_template: String,
+
+    /// format of the generated images (png, jpeg, bmp, tiff, ...), guessed from --out by default
+    #[arg(long = "format", value_parser = image_format)]
+    format: Option<ImageFormat>,
+
+    /// save the intermediate images (blur, edge, mask, points and wireframe) of each image in this directory
+    #[arg(long = "debug-dir")]
+    debug_dir: Option<String>,
+
+    /// number of images processed in parallel (default to the number of cpus)
+    #[arg(long = "jobs")]
+    jobs: Option<usize>,
+
+    /// process the inputs in order as the frames of a video, keeping the points where the edges did not change
+    #[arg(long = "sequence", default_value_t = false, requires = "out_dir", conflicts_with_all = ["animation", "jobs"])]
+    sequence: bool,
+
+    /// largest change of the edge strength (0-255) under a point for it to be kept in the next frame
+    #[arg(long = "sequence-tolerance", default_value_t = sequence::DEFAULT_TOLERANCE, requires = "sequence")]
+    sequence_tolerance: u8,
+
+    /// only print the errors
+    #[arg(long = "quiet", short = 'q', default_value_t = false, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// print the duration of each stage
+    #[arg(long = "verbose", short = 'v', default_value_t = false)]
+    verbose: bool,
+
+    /// also print the quality of the triangles and of the generated image (angles, aspect ratios, areas, MSE, PSNR, SSIM), even with --quiet
+    #[arg(long = "stats", default_value_t = false, conflicts_with = "animation")]
+    stats: bool,
+
+    /// print a machine-readable summary in JSON (on stderr when the image is written to stdout)
+    #[arg(long = "json", default_value_t = false)]
+    json: bool,
+
+    /// load the settings from a TOML or JSON file (command line options take precedence)
+    #[arg(long = "config", conflicts_with = "preset")]
+    config: Option<String>,
+
+    /// start from a built-in style (command line options take precedence)
+    #[arg(long = "preset")]
+    preset: Option<Preset>,
+
+    /// list the built-in styles
+    #[arg(long = "list-presets", default_value_t = false)]
+    list_presets: bool,
+
+    /// save the effective settings to a TOML or JSON file
+    #[arg(long = "dump-config")]
+    dump_config: Option<String>,
     
     /// do not fill triangle in generated image, only stroke 
-    #[arg(long = "ow", default_value_t = false)]
+    #[arg(long = "ow", default_value_t = false, overrides_with = "no_only_wireframe")]
     only_wireframe: bool,
+
+    /// fill the triangles, overrides `only_wireframe = true` from --config or --preset
+    #[arg(long = "no-ow", default_value_t = false, overrides_with = "only_wireframe")]
+    no_only_wireframe: bool,
+
+    /// only draw a circle at each vertex (constellation), requires --vr
+    #[arg(long = "op", default_value_t = false, conflicts_with_all = ["only_wireframe", "morph", "export_mesh"], overrides_with = "no_only_points")]
+    only_points: bool,
+
+    /// draw the triangles, overrides `only_points = true` from --config or --preset
+    #[arg(long = "no-op", default_value_t = false, overrides_with = "only_points")]
+    no_only_points: bool,
+
+    /// radius of the circle drawn at each vertex (0 mean no circle)
+    #[arg(long = "vr", default_value_t = 0.0, conflicts_with = "morph")]
+    vertex_radius: f64,
+
+    /// vertex circle color, same formats as --bc (default: the color of the source image under the vertex)
+    #[arg(long = "vc")]
+    vertex_color: Option<Color>,
+
+    /// vertex circle radius: fixed (--vr) or edge (larger on the strongest edges, up to --vr)
+    #[arg(long = "vertex-sizing")]
+    vertex_sizing: Option<VertexSizing>,
     
-    /// stroke width in the generated image
+    /// stroke width in the generated image (0 mean no stroke)
     #[arg(long = "sw", default_value_t = 0.1)]
-    stroke_witdh: f64,
+    stroke_width: f64,
 
-     /// whenever the generated image should have a background
-     #[arg(long = "wb")]
-     with_background: bool,
+    /// whenever the generated image should have a background (`--wb` alone means true)
+    #[arg(long = "wb", default_value_t = true, action = ArgAction::Set, num_args = 0..=1, default_missing_value = "true")]
+    with_background: bool,
     
-    /// background color in the generated image in hex format 
-    #[arg(long="bc", value_parser = color_from_hex)]
-    background_color: Option<usvg::Color>,
+    /// background color in the generated image (#RGB, #RRGGBBAA, rgb(..), hsl(..), CSS name, ...)
+    #[arg(long="bc")]
+    background_color: Option<Color>,
 
-    /// stroke color in the generated image in hex format 
-    #[arg(long = "sc", value_parser = color_from_hex)]
-    stroke_color: Option<usvg::Color>,
-}
+    /// stroke color in the generated image, same formats as --bc
+    #[arg(long = "sc")]
+    stroke_color: Option<Color>,
+
+    /// stroke color mode: fixed (--sc), fill, darker[:FACTOR], lighter[:FACTOR] or sampled
+    #[arg(long = "stroke-mode")]
+    stroke_mode: Option<StrokeMode>,
+
+    /// rasterizer: svg (resvg) or raster (direct tiny-skia, faster on large meshes)
+    #[arg(long = "backend")]
+    backend: Option<RenderBackend>,
+
+    /// do not antialias the edges shared by two triangles, so the background never shows between them
+    #[arg(long = "seamless", default_value_t = false, overrides_with = "no_seamless")]
+    seamless: bool,
+
+    /// antialias every edge, overrides `seamless = true` from --config or --preset
+    #[arg(long = "no-seamless", default_value_t = false, overrides_with = "seamless")]
+    no_seamless: bool,
+
+    /// render a gif or apng of the triangulation building up, by insertion, distance, size or points
+    #[arg(long = "animate")]
+    animate: Option<BuildOrder>,
+
+    /// render a gif or apng of the source image morphing into this image
+    #[arg(long = "morph")]
+    morph: Option<String>,
+
+    /// number of frames of the animation
+    #[arg(long = "frames", default_value_t = 30, requires = "animation")]
+    frames: usize,
+
+    /// duration of each frame of the animation, in milliseconds
+    #[arg(long = "frame-delay", default_value_t = 80, requires = "animation")]
+    frame_delay: u16,
+
+    /// duration of the last frame of the animation, in milliseconds
+    #[arg(long = "hold", default_value_t = 1000, requires = "animation")]
+    hold: u16,
+
+    /// points (csv or json file) added to the points detected on the edges, see --points-mode
+    #[arg(long = "points", conflicts_with = "sequence")]
+    points: Option<String>,
+
+    /// merge the --points with the detected points, or replace them (no edge detection)
+    #[arg(long = "points-mode", default_value_t = PointsMode::Merge, requires = "points")]
+    points_mode: PointsMode,
+
+    /// draw the triangles of a mesh file (json or obj, see --export-mesh) instead of triangulating the image
+    #[arg(long = "mesh", conflicts_with_all = ["points", "sequence", "animation"])]
+    mesh: Option<String>,
+
+    /// also write the triangles as a mesh (json, obj, ply or geojson file)
+    #[arg(long = "export-mesh", conflicts_with_all = ["animation", "out_dir"])]
+    export_mesh: Option<String>,
+
+    /// format of the mesh: json, obj (with a mtl file), obj-colors (vertex colors), ply or geojson,
+    /// guessed from --export-mesh by default
+    #[arg(long = "mesh-format", requires = "export_mesh")]
+    mesh_format: Option<MeshFormat>,
+
+    /// snap the fill colors to a palette file (one color per line, or a GIMP .gpl palette)
+    #[arg(long = "palette", conflicts_with = "palette_size")]
+    palette: Option<String>,
+
+    /// snap the fill colors to a palette of N colors extracted from the source image
+    #[arg(long = "palette-size")]
+    palette_size: Option<usize>,
+
+    /// algorithm extracting the palette of --palette-size
+    #[arg(long = "palette-method", value_enum, default_value_t = PaletteMethod::KMeans, requires = "palette_size")]
+    palette_method: PaletteMethod,
+
+    /// brightness of the triangle colors, between -1 (black) and 1 (white)
+    #[arg(long = "brightness", allow_negative_numbers = true)]
+    brightness: Option<f64>,
+
+    /// contrast factor of the triangle colors (0 gives a flat gray, 1 keeps the colors)
+    #[arg(long = "contrast")]
+    contrast: Option<f64>,
+
+    /// saturation factor of the triangle colors (0 gives grays, 1 keeps the colors)
+    #[arg(long = "saturation")]
+    saturation: Option<f64>,
+
+    /// rotation of the hue of the triangle colors, in degrees
+    #[arg(long = "hue", allow_negative_numbers = true)]
+    hue: Option<f64>,
+
+    /// blend the triangle colors with a sepia tone, between 0 and 1
+    #[arg(long = "sepia")]
+    sepia: Option<f64>,
+
+    /// map the luminance of the triangle colors between two colors
+    #[arg(long = "duotone", num_args = 2, value_names = ["SHADOWS", "HIGHLIGHTS"])]
+    duotone: Option<Vec<Color>>,
 
-fn color_from_hex(hex: &str) -> Result<usvg::Color, String> {
-    let parsing_error = "invalid hex color !";
-    let hex = hex.trim_start_matches('#');
-    let r = u8::from_str_radix(&hex[0..2], 16)
-        .map_err(|_| parsing_error)?;
-    let g = u8::from_str_radix(&hex[2..4], 16)
-        .map_err(|_| parsing_error)?;
-    let b = u8::from_str_radix(&hex[4..6], 16)
-        .map_err(|_| parsing_error)?;
+    /// number of levels kept per color channel
+    #[arg(long = "posterize")]
+    posterize: Option<u8>,
 
-    Ok(usvg::Color { red: r, green: g, blue: b})
+    /// width of the generated image (keep the aspect ratio unless --stretch is set)
+    #[arg(long = "width", conflicts_with = "scale")]
+    width: Option<u32>,
+
+    /// height of the generated image (keep the aspect ratio unless --stretch is set)
+    #[arg(long = "height", conflicts_with = "scale")]
+    height: Option<u32>,
+
+    /// scale factor of the generated image relative to the source image
+    #[arg(long = "scale", value_parser = positive_float)]
+    scale: Option<f64>,
+
+    /// use exactly --width and --height, ignoring the aspect ratio
+    #[arg(long = "stretch", default_value_t = false, requires_all = ["width", "height"])]
+    stretch: bool,
 }
 
-fn create_triangulation(args : &Arguments) -> super::Triangulation {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum PaletteMethod {
+    MedianCut,
+    KMeans,
+}
 
-    super::Triangulation {
-        blur_factor: args.blur_factor, 
-        sobel_factor: args.sobel_factor, 
-        points_threshold: args.points_threshold, 
-        point_rate: args.point_rate, 
-        max_points: args.max_points, 
-        grayscale: args.grayscale,
-        ..Default::default()
-        
+fn positive_float(value: &str) -> Result<f64, String> {
+    let value: f64 = value.parse().map_err(|_| "invalid number !")?;
+    if value.is_finite() && value > 0.0 {
+        Ok(value)
+    } else {
+        Err("must be a positive number !".to_owned())
     }
+}
+
+fn image_format(value: &str) -> Result<ImageFormat, String> {
+    ImageFormat::from_extension(value.to_lowercase())
+        .ok_or_else(|| format!("unknown image format `{}`", value))
+}
+
+/// Returns whenever the option `id` must override the value of the configuration file or preset.
+/// Without configuration file or preset, every option (default values included) is applied.
+fn is_overridden(matches: &ArgMatches, has_config: bool, id: &str) -> bool {
+    !has_config || matches.value_source(id) == Some(ValueSource::CommandLine)
+}
+
+fn create_triangulation(args : &Arguments, matches: &ArgMatches, config: &Config) -> crate::Result<Triangulation> {
+    let has_config = args.config.is_some() || args.preset.is_some();
+    let overridden = |id| is_overridden(matches, has_config, id);
+    let mut triangulation = config.triangulation.clone();
+
+    if overridden("blur_factor") { triangulation.blur_factor = args.blur_factor; }
+    if overridden("sobel_factor") { triangulation.sobel_factor = args.sobel_factor; }
+    if overridden("points_threshold") { triangulation.points_threshold = args.points_threshold; }
+    if overridden("point_rate") { triangulation.point_rate = args.point_rate; }
+    if overridden("max_points") { triangulation.max_points = args.max_points; }
+    if args.no_grayscale { triangulation.grayscale = false; } else if overridden("grayscale") { triangulation.grayscale = args.grayscale; }
+    if args.work_size.is_some() { triangulation.work_size = args.work_size; }
+    if args.seed.is_some() { triangulation.seed = args.seed; }
+
+    TriangulationBuilder::from(triangulation).build()
 } 
 
-fn create_drawer(args : &Arguments) -> Drawer {
-    Drawer {
-        only_wireframe: args.only_wireframe, 
-        stroke_color: args.stroke_color,
-        background_color: args.background_color, 
-        ..Default::default()
+fn create_drawer(args : &Arguments, matches: &ArgMatches, config: &Config) -> crate::Result<Drawer> {
+    let has_config = args.config.is_some() || args.preset.is_some();
+    let overridden = |id| is_overridden(matches, has_config, id);
+    let mut drawer = config.drawer.clone();
+
+    if args.no_only_wireframe { drawer.only_wireframe = false; } else if overridden("only_wireframe") { drawer.only_wireframe = args.only_wireframe; }
+    if args.no_only_points { drawer.only_points = false; } else if overridden("only_points") { drawer.only_points = args.only_points; }
+    if overridden("stroke_width") { drawer.stroke_width = args.stroke_width; }
+    if overridden("vertex_radius") { drawer.vertex_radius = args.vertex_radius; }
+    if let Some(color) = args.vertex_color {
+        drawer.vertex_color = Some(color.to_usvg());
+        drawer.vertex_opacity = color.opacity();
+    }
+    if let Some(sizing) = args.vertex_sizing { drawer.vertex_sizing = sizing; }
+    if overridden("with_background") { drawer.with_background = args.with_background; }
+    if let Some(color) = args.stroke_color {
+        drawer.stroke_color = Some(color.to_usvg());
+        drawer.stroke_opacity = color.opacity();
+    }
+    if let Some(mode) = args.stroke_mode { drawer.stroke_mode = mode; }
+    if let Some(backend) = args.backend { drawer.backend = backend; }
+    if args.no_seamless { drawer.seamless = false; } else if overridden("seamless") { drawer.seamless = args.seamless; }
+    if let Some(color) = args.background_color {
+        drawer.background_color = Some(color.to_usvg());
+        drawer.background_opacity = color.opacity();
+    }
+    if let Some(brightness) = args.brightness { drawer.adjust.brightness = brightness; }
+    if let Some(contrast) = args.contrast { drawer.adjust.contrast = contrast; }
+    if let Some(saturation) = args.saturation { drawer.adjust.saturation = saturation; }
+    if let Some(hue) = args.hue { drawer.adjust.hue = hue; }
+    if let Some(sepia) = args.sepia { drawer.adjust.sepia = sepia; }
+    if let Some([shadows, highlights]) = args.duotone.as_deref() {
+        drawer.adjust.duotone = Some(Duotone { shadows: *shadows, highlights: *highlights });
+    }
+    if args.posterize.is_some() { drawer.adjust.posterize = args.posterize; }
+    if let Some(path) = &args.palette {
+        drawer.palette = Some(Palette::load(path)?);
+    }
+    if let Some(size) = args.palette_size {
+        drawer.palette = Some(match args.palette_method {
+            PaletteMethod::MedianCut => Palette::MedianCut(size),
+            PaletteMethod::KMeans => Palette::KMeans(size),
+        });
+    }
+
+    DrawerBuilder::from(drawer).build()
+}
+
+fn create_animation(args: &Arguments) -> crate::Result<Option<Animation>> {
+    let Some(order) = args.animate else {
+        return Ok(None);
+    };
+    let animation = Animation { order, timing: create_timing(args) };
+    animation.validate()?;
+    Ok(Some(animation))
+}
+
+fn create_timing(args: &Arguments) -> Timing {
+    Timing { frames: args.frames, delay: args.frame_delay, hold: args.hold }
+}
+
+fn create_morph(args: &Arguments, drawer: &Drawer) -> crate::Result<Option<(Morph, PathBuf)>> {
+    let Some(target) = &args.morph else {
+        return Ok(None);
+    };
+    let morph = Morph { timing: create_timing(args) };
+    morph.validate()?;
+    Morph::validate_drawer(drawer)?;
+    Ok(Some((morph, PathBuf::from(target))))
+}
+
+fn create_mesh_export(args: &Arguments, drawer: &Drawer) -> crate::Result<Option<(PathBuf, MeshFormat)>> {
+    let Some(path) = &args.export_mesh else {
+        return Ok(None);
+    };
+    if drawer.only_points {
+        return Err(crate::Error::invalid_parameter("export_mesh", "a drawing of only the points has no triangle to export"));
+    }
+    let format = args.mesh_format
+        .or_else(|| MeshFormat::from_path(path))
+        .ok_or_else(|| crate::Error::invalid_parameter(
+            "export_mesh",
+            format!("unknown format for `{}` (expected a .json, .obj, .ply or .geojson file, or --mesh-format)", path),
+        ))?;
+    Ok(Some((PathBuf::from(path), format)))
+}
+
+fn create_fit(args: &Arguments) -> Fit {
+    match (args.width, args.height, args.scale) {
+        (_, _, Some(scale)) => Fit::Zoom(scale),
+        (Some(width), Some(height), _) if args.stretch => Fit::Stretch(width, height),
+        (Some(width), Some(height), _) => Fit::Size(width, height),
+        (Some(width), None, _) => Fit::Width(width),
+        (None, Some(height), _) => Fit::Height(height),
+        (None, None, None) => Fit::Original,
     }
 }
 
@@ -109,35 +449,723 @@ fn format_success(message: &str) -> String {
     format!("{} {}", "\u{2714}".green().to_owned(), message.to_owned())
 }
 
-pub fn execute() -> Result<()>{
-    let args = Arguments::parse(); 
+/// Category of a failure, each category exits with its own code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorKind {
+    /// the command line can not be parsed
+    Usage,
+    /// the options, configuration file or preset are invalid
+    InvalidSettings,
+    /// an input image could not be read or decoded
+    Input,
+    /// no triangle could be generated from an input image
+    Triangulation,
+    /// the generated image could not be rendered or written
+    Output,
+    /// some images of a batch failed
+    PartialFailure,
+}
 
-    let input_image = ImageReader::open(&args.input)
-        .with_context(|| format_error("could not open input image"))?
-        .decode()?;
+impl ErrorKind {
 
-    let triangulation = create_triangulation(&args);
-    let drawer = create_drawer(&args);
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ErrorKind::Usage => 2,
+            ErrorKind::InvalidSettings => 3,
+            ErrorKind::Input => 4,
+            ErrorKind::Triangulation => 5,
+            ErrorKind::Output => 6,
+            ErrorKind::PartialFailure => 7,
+        }
+    }
+}
 
-    let mut sp = Spinner::new(Spinners::Dots, format_success("start generating delaunay image ...."));
-    let (triangles, source_image) = triangulation.generate_triangle(input_image);
-    
-    if triangles.is_empty() {
-        sp.stop_with_newline();
-        return Err(anyhow!(format_error("could not generate delaunay triangles")))
+/// Error returned by [`execute`], `kind` gives the exit code of the process.
+#[derive(Debug)]
+pub struct CliError {
+    pub kind: ErrorKind,
+    pub error: anyhow::Error,
+}
+
+impl CliError {
+
+    /// Prints the error and its causes on stderr.
+    pub fn print(&self) {
+        eprintln!("{}", format_error(&format!("{:?}", self.error)));
     }
+}
 
-    let result_image =  drawer.draw(source_image, triangles)
-        .ok_or_else(|| {
-            sp.stop_with_newline();
-            anyhow!(format_error("error occure during image generation. please retry")) 
-        })?;
+impl fmt::Display for CliError {
 
-    sp.stop_with_newline();
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#}", self.error)
+    }
+}
+
+trait ErrorKindExt<T> {
+    fn kind(self, kind: ErrorKind) -> Result<T, CliError>;
+}
+
+impl<T> ErrorKindExt<T> for Result<T> {
+
+    fn kind(self, kind: ErrorKind) -> Result<T, CliError> {
+        self.map_err(|error| CliError { kind, error })
+    }
+}
+
+/// Where and how much the command prints.
+#[derive(Debug, Clone, Copy)]
+struct Console {
+    quiet: bool,
+    verbose: bool,
+    json: bool,
+    /// the generated image is written to stdout, messages must go to stderr
+    image_on_stdout: bool,
+}
+
+impl Console {
 
-    result_image.save_as_png(&args.output)
-        .map_err(|_| anyhow!(format_error("could not save output image")))?;
+    fn message(&self, message: &str) {
+        if self.quiet || self.json {
+            return;
+        }
+        if self.image_on_stdout {
+            eprintln!("{}", message);
+        } else {
+            println!("{}", message);
+        }
+    }
+
+    /// Prints what an option asked for, even with --quiet, the json summary already has it.
+    fn result(&self, message: &str) {
+        if self.json {
+            return;
+        }
+        if self.image_on_stdout {
+            eprintln!("{}", message);
+        } else {
+            println!("{}", message);
+        }
+    }
+
+    fn detail(&self, message: &str) {
+        if self.verbose {
+            eprintln!("{}", message);
+        }
+    }
+
+    fn json(&self, value: &impl Serialize) {
+        let json = serde_json::to_string_pretty(value).unwrap_or_default();
+        if self.image_on_stdout {
+            eprintln!("{}", json);
+        } else {
+            println!("{}", json);
+        }
+    }
+
+    /// The spinner is only shown in an interactive terminal, it would fill the logs with control characters.
+    fn spinner(&self, message: &str) -> Option<Spinner> {
+        let interactive = !(self.quiet || self.verbose || self.json || self.image_on_stdout);
+        if interactive && io::stdout().is_terminal() {
+            Some(Spinner::new(Spinners::Dots, format_success(message)))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct StageTiming {
+    stage: &'static str,
+    duration_ms: f64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Ok,
+    Error,
+}
+
+/// Outcome of the processing of an image.
+#[derive(Debug, Serialize)]
+struct FileReport {
+    input: String,
+    output: String,
+    status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    points: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    triangles: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<Stats>,
+    timings: Vec<StageTiming>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_kind: Option<ErrorKind>,
+}
+
+impl FileReport {
+
+    fn new(input: &Path, output: &Path) -> Self {
+        Self {
+            input: input.display().to_string(),
+            output: output.display().to_string(),
+            status: Status::Ok,
+            points: None,
+            triangles: None,
+            stats: None,
+            timings: vec![],
+            error: None,
+            error_kind: None,
+        }
+    }
+
+    fn timed<T>(&mut self, stage: &'static str, task: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = task();
+        self.timings.push(StageTiming { stage, duration_ms: duration_ms(start.elapsed()) });
+        result
+    }
+}
+
+/// Machine-readable summary printed by --json.
+#[derive(Debug, Serialize)]
+struct Summary {
+    status: Status,
+    files: Vec<FileReport>,
+    succeeded: usize,
+    failed: usize,
+    duration_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_kind: Option<ErrorKind>,
+}
+
+fn duration_ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Settings shared by every image of a run.
+struct Pipeline {
+    triangulation: Triangulation,
+    drawer: Drawer,
+    /// renders an animation of the triangulation building up instead of a still image
+    animation: Option<Animation>,
+    /// renders the morph into the target image instead of a still image
+    morph: Option<(Morph, PathBuf)>,
+    /// samples the points of the inputs as the frames of a video
+    sequence: Option<Mutex<Sequence>>,
+    /// also writes the mesh of the triangles to this file
+    mesh_export: Option<(PathBuf, MeshFormat)>,
+    /// points added to the sampled points or replacing them
+    points: Option<PointSet>,
+    /// draws the triangles of this mesh file instead of triangulating the image
+    mesh: Option<PathBuf>,
+    /// saves the intermediate images in this directory
+    debug_dir: Option<PathBuf>,
+    /// measures the triangles and the generated image
+    stats: bool,
+    fit: Fit,
+    format: Option<ImageFormat>,
+}
+
+pub fn execute() -> Result<(), CliError> {
+    let start = Instant::now();
+    let matches = Arguments::command().get_matches();
+    let args = Arguments::from_arg_matches(&matches)
+        .map_err(anyhow::Error::from)
+        .kind(ErrorKind::Usage)?;
+    let console = Console {
+        quiet: args.quiet,
+        verbose: args.verbose,
+        json: args.json,
+        image_on_stdout: args.output.as_deref() == Some(STDIO),
+    };
+
+    if args.list_presets {
+        list_presets(console);
+        return Ok(());
+    }
+
+    let mut files = vec![];
+    let result = run(&args, &matches, console, &mut files);
+
+    if console.json {
+        let failed = files.iter().filter(|file| matches!(file.status, Status::Error)).count();
+        let summary = Summary {
+            status: if result.is_ok() { Status::Ok } else { Status::Error },
+            succeeded: files.len() - failed,
+            failed,
+            files,
+            duration_ms: duration_ms(start.elapsed()),
+            error: result.as_ref().err().map(|e| e.to_string()),
+            error_kind: result.as_ref().err().map(|e| e.kind),
+        };
+        console.json(&summary);
+    }
+    result
+}
+
+/// Preset listed by --list-presets --json.
+#[derive(Debug, Serialize)]
+struct PresetEntry {
+    name: &'static str,
+    description: &'static str,
+}
+
+/// Prints the built-in styles, as a json array with --json.
+fn list_presets(console: Console) {
+    if console.json {
+        let presets: Vec<PresetEntry> = Preset::ALL.iter()
+            .map(|preset| PresetEntry { name: preset.name(), description: preset.description() })
+            .collect();
+        console.json(&presets);
+        return;
+    }
+    for preset in Preset::ALL {
+        println!("{:<10} {}", preset.name(), preset.description());
+    }
+}
+
+fn run(args: &Arguments, matches: &ArgMatches, console: Console, files: &mut Vec<FileReport>) -> Result<(), CliError> {
+    let config = match (&args.config, args.preset) {
+        (Some(path), _) => Config::load(path)
+            .with_context(|| format!("could not load the configuration file {}", path))
+            .kind(ErrorKind::InvalidSettings)?,
+        (None, Some(preset)) => preset.config(),
+        (None, None) => Config::default(),
+    };
+
+    let triangulation = create_triangulation(args, matches, &config)
+        .with_context(|| "invalid triangulation options")
+        .kind(ErrorKind::InvalidSettings)?;
+    let drawer = create_drawer(args, matches, &config)
+        .with_context(|| "invalid drawing options")
+        .kind(ErrorKind::InvalidSettings)?;
+    let animation = create_animation(args)
+        .with_context(|| "invalid animation options")
+        .kind(ErrorKind::InvalidSettings)?;
+    let morph = create_morph(args, &drawer)
+        .with_context(|| "invalid morph options")
+        .kind(ErrorKind::InvalidSettings)?;
+    let mesh_export = create_mesh_export(args, &drawer)
+        .with_context(|| "invalid mesh options")
+        .kind(ErrorKind::InvalidSettings)?;
+    let points = args.points.as_ref()
+        .map(|path| PointSet::load(path, args.points_mode))
+        .transpose()
+        .with_context(|| "could not load the points")
+        .kind(ErrorKind::InvalidSettings)?;
+
+    if let Some(path) = &args.dump_config {
+        let effective_config = Config { triangulation: triangulation.clone(), drawer: drawer.clone() };
+        effective_config.save(path)
+            .with_context(|| format!("could not save the configuration file {}", path))
+            .kind(ErrorKind::Output)?;
+        console.message(&format_success(&format!("configuration saved in {}", path)));
+    }
+
+    if args.input.is_empty() {
+        return Ok(());
+    }
+
+    let inputs = if args.input == [STDIO] {
+        vec![PathBuf::from(STDIO)]
+    } else if args.input.iter().any(|input| input == STDIO) {
+        return Err(anyhow!("stdin (-) can not be combined with other inputs"))
+            .kind(ErrorKind::Usage);
+    } else {
+        batch::collect_inputs(&args.input)
+            .with_context(|| "could not find the input images")
+            .kind(ErrorKind::Input)?
+    };
+    let sequence = if args.sequence {
+        let sequence = Sequence::new(triangulation.clone(), args.sequence_tolerance)
+            .with_context(|| "invalid sequence options")
+            .kind(ErrorKind::InvalidSettings)?;
+        Some(Mutex::new(sequence))
+    } else {
+        None
+    };
+    if let Some(debug_dir) = &args.debug_dir {
+        fs::create_dir_all(debug_dir)
+            .with_context(|| format!("could not create the debug directory {}", debug_dir))
+            .kind(ErrorKind::Output)?;
+    }
+    let pipeline = Pipeline {
+        triangulation,
+        drawer,
+        animation,
+        morph,
+        sequence,
+        mesh_export,
+        points,
+        mesh: args.mesh.as_ref().map(PathBuf::from),
+        debug_dir: args.debug_dir.as_ref().map(PathBuf::from),
+        stats: args.stats,
+        fit: create_fit(args),
+        format: args.format,
+    };
+
+    match (&args.output, &args.out_dir) {
+        (Some(output), _) => {
+            let [input] = inputs.as_slice() else {
+                return Err(anyhow!(
+                    "{} input images found, use --out-dir to process several images", inputs.len()
+                )).kind(ErrorKind::Usage);
+            };
+
+            let mut report = FileReport::new(input, Path::new(output));
+            let mut sp = console.spinner("start generating delaunay image ....");
+            let result = process_file(input, Path::new(output), &pipeline, &mut report);
+            if let Some(sp) = sp.as_mut() {
+                sp.stop_with_newline();
+            }
+            print_timings(console, &report);
+            print_stats(console, &report);
+            files.push(report);
+
+            result.map_err(|e| CliError {
+                kind: e.kind,
+                error: e.error.context(format!("could not process {}", input.display())),
+            })?;
+
+            console.message(&format_success("done (delaunay image is saved)"));
+            Ok(())
+        }
+        (None, Some(out_dir)) => {
+            let out_dir = PathBuf::from(out_dir);
+            fs::create_dir_all(&out_dir)
+                .with_context(|| format!("could not create the output directory {}", out_dir.display()))
+                .kind(ErrorKind::Output)?;
+            // the default file names take the extension of --format
+            let template = match args.format {
+                Some(format) if matches.value_source("name_template") == Some(ValueSource::CommandLine) => {
+                    batch::check_template(&args.name_template, format)
+                        .with_context(|| "invalid output file name")
+                        .kind(ErrorKind::InvalidSettings)?;
+                    args.name_template.clone()
+                }
+                Some(format) => batch::default_template(format),
+                None => args.name_template.clone(),
+            };
+            let outputs = batch::output_paths(&out_dir, &template, &inputs)
+                .with_context(|| "invalid output file name")
+                .kind(ErrorKind::InvalidSettings)?;
+
+            // the frames of a sequence depend on the previous one
+            let jobs = args.jobs
+                .or_else(|| thread::available_parallelism().ok().map(|jobs| jobs.get()))
+                .filter(|_| pipeline.sequence.is_none())
+                .unwrap_or(1);
+            let jobs_files: Vec<(PathBuf, PathBuf)> = inputs.into_iter().zip(outputs).collect();
+            let results = batch::run_parallel(&jobs_files, jobs, |(input, output)| {
+                let mut report = FileReport::new(input, output);
+                let result = process_file(input, output, &pipeline, &mut report);
+                (report, result)
+            });
+
+            let mut failures = 0;
+            for (report, result) in results {
+                match result {
+                    Ok(()) => console.message(&format_success(&format!("{} -> {}", report.input, report.output))),
+                    Err(e) => {
+                        failures += 1;
+                        eprintln!("{}", format_error(&format!("{}: {}", report.input, e)));
+                    }
+                }
+                print_timings(console, &report);
+                print_stats(console, &report);
+                files.push(report);
+            }
+
+            let total = files.len();
+            let summary = format!("{} image(s) processed, {} succeeded, {} failed", total, total - failures, failures);
+            if failures > 0 {
+                return Err(anyhow!(summary)).kind(ErrorKind::PartialFailure);
+            }
+            console.message(&format_success(&summary));
+            Ok(())
+        }
+        (None, None) => unreachable!("clap requires --out or --out-dir"),
+    }
+}
+
+fn print_timings(console: Console, report: &FileReport) {
+    if let (Some(points), Some(triangles)) = (report.points, report.triangles) {
+        console.detail(&format!("{}: {} points, {} triangles", report.input, points, triangles));
+    }
+    for timing in &report.timings {
+        console.detail(&format!("  {:<14} {:>10.1} ms", timing.stage, timing.duration_ms));
+    }
+}
+
+fn print_stats(console: Console, report: &FileReport) {
+    if let Some(stats) = &report.stats {
+        console.result(&format!("{}:\n{}", report.input, stats));
+    }
+}
+
+/// Path used by --in and --out for stdin and stdout.
+const STDIO: &str = "-";
+
+fn read_image(input: &Path) -> Result<DynamicImage> {
+    let reader = if input == Path::new(STDIO) {
+        let mut bytes = vec![];
+        io::stdin().lock().read_to_end(&mut bytes)
+            .context("could not read stdin")?;
+        ImageReader::new(Cursor::new(bytes))
+            .with_guessed_format()
+            .context("could not guess the format of stdin")?
+            .decode()
+    } else {
+        ImageReader::open(input)
+            .context("could not open input image")?
+            .decode()
+    };
+
+    reader.context("could not decode input image")
+}
+
+fn process_file(input: &Path, output: &Path, pipeline: &Pipeline, report: &mut FileReport) -> Result<(), CliError> {
+    let result = generate_file(input, output, pipeline, report);
+    if let Err(e) = &result {
+        report.status = Status::Error;
+        report.error = Some(e.to_string());
+        report.error_kind = Some(e.kind);
+    }
+    result
+}
+
+fn generate_file(input: &Path, output: &Path, pipeline: &Pipeline, report: &mut FileReport) -> Result<(), CliError> {
+    let triangulation = &pipeline.triangulation;
+    let input_image = report.timed("decode", || read_image(input))
+        .kind(ErrorKind::Input)?;
+
+    let source_image = report.timed("prepare", || triangulation.prepare(input_image))
+        .context("could not prepare the source image")
+        .kind(ErrorKind::Triangulation)?;
+    // the edges detected for the debug images or the markers are reused to sample the points
+    let mut edges = None;
+    if pipeline.debug_dir.is_some() && pipeline.mesh.is_none() {
+        let mut stages = vec![];
+        edges = Some(triangulation.inspect_edges(&source_image, |stage, image| stages.push((stage, image.clone())))
+            .context("could not detect the edges")
+            .kind(ErrorKind::Triangulation)?);
+        for (stage, image) in stages {
+            save_debug(pipeline, report, stage, &image)?;
+        }
+    } else if pipeline.drawer.sizes_with_edges() {
+        edges = Some(report.timed("detect edges", || triangulation.detect_edges(&source_image))
+            .context("could not detect the edges")
+            .kind(ErrorKind::Triangulation)?);
+    }
+    if let Some(path) = &pipeline.mesh {
+        let (width, height) = source_image.dimensions();
+        let triangles = report.timed("load mesh", || mesh::load_triangles(path, width, height))
+            .with_context(|| format!("could not load the mesh {}", path.display()))
+            .kind(ErrorKind::Input)?;
+        return draw_file(source_image, triangles, edges.as_ref(), output, pipeline, report);
+    }
+
+    let points = report.timed("sample points", || match (&pipeline.sequence, &pipeline.points, &edges) {
+        (Some(sequence), _, Some(edges)) => sequence.lock().unwrap().sample_edges(edges),
+        (Some(sequence), _, None) => sequence.lock().unwrap().sample_points(&source_image),
+        (None, point_set, Some(edges)) => triangulation.sample_edges(edges, point_set.as_ref()),
+        (None, Some(point_set), None) => triangulation.sample_points_with(&source_image, point_set),
+        (None, None, None) => triangulation.sample_points(&source_image),
+    })
+    .context("could not sample the points")
+    .kind(ErrorKind::Triangulation)?;
+    if pipeline.debug_dir.is_some() {
+        save_debug(pipeline, report, Stage::Points, &debug::points_overlay(&source_image, &points))?;
+    }
+    report.points = Some(points.len());
+    if let Some(animation) = &pipeline.animation {
+        let drawings = report.timed("draw", || animation.drawings(&pipeline.drawer, &source_image, points, edges.as_ref()))
+            .context("could not draw the animation")
+            .kind(ErrorKind::Output)?;
+        report.triangles = drawings.last().map(|drawing| drawing.shapes().len());
+        return write_frames(&drawings, output, pipeline, animation.timing, report);
+    }
+    if let Some((morph, target)) = &pipeline.morph {
+        let target_image = report.timed("decode target", || read_image(target))
+            .with_context(|| format!("could not read the morph target {}", target.display()))
+            .kind(ErrorKind::Input)?;
+        let target_image = report.timed("prepare target", || triangulation.prepare(target_image))
+            .context("could not prepare the morph target")
+            .kind(ErrorKind::Triangulation)?;
+        let target_points = report.timed("sample target", || triangulation.sample_points(&target_image))
+            .context("could not sample the points of the morph target")
+            .kind(ErrorKind::Triangulation)?;
+        let drawings = report.timed("draw", || {
+            morph.drawings(&pipeline.drawer, &source_image, points, &target_image, target_points)
+        })
+        .context("could not draw the morph")
+        .kind(ErrorKind::Output)?;
+        report.triangles = drawings.last().map(|drawing| drawing.shapes().len());
+        return write_frames(&drawings, output, pipeline, morph.timing, report);
+    }
+    let triangles = report.timed("triangulate", || triangulation.triangulate(&source_image, points))
+        .context("could not generate delaunay triangles")
+        .kind(ErrorKind::Triangulation)?;
+    draw_file(source_image, triangles, edges.as_ref(), output, pipeline, report)
+}
+
+/// Draws the triangles of the source image and writes the image (and the mesh) of the file.
+fn draw_file(
+    source_image: RgbaImage,
+    triangles: Vec<Triangle>,
+    edges: Option<&Edges>,
+    output: &Path,
+    pipeline: &Pipeline,
+    report: &mut FileReport,
+) -> Result<(), CliError> {
+    report.triangles = Some(triangles.len());
+    if pipeline.debug_dir.is_some() {
+        let wireframe = debug::wireframe(&source_image, &triangles)
+            .context("could not draw the wireframe")
+            .kind(ErrorKind::Output)?;
+        save_debug(pipeline, report, Stage::Wireframe, &wireframe)?;
+    }
+
+    let stats_input = pipeline.stats.then(|| (source_image.clone(), triangles.clone()));
+    let result_image = report.timed("draw", || pipeline.drawer.draw_with_edges(source_image, triangles, edges))
+        .context("could not draw the delaunay image")
+        .kind(ErrorKind::Output)?;
+
+    // the image rendered for the stats is the one written, it is not rendered twice
+    let mut rendered_image = None;
+    if let Some((source_image, triangles)) = stats_input {
+        let rendered = report.timed("render", || result_image.to_image_with(pipeline.fit))
+            .context("could not render the delaunay image")
+            .kind(ErrorKind::Output)?;
+        let stats = report.timed("stats", || {
+            // when --width, --height or --scale resize the output, the source image is resized too
+            let (width, height) = rendered.dimensions();
+            let source_image = if source_image.dimensions() == (width, height) {
+                source_image
+            } else {
+                imageops::resize(&source_image, width, height, imageops::FilterType::Triangle)
+            };
+            Stats::new(&source_image, &triangles, &rendered)
+        })
+        .context("could not measure the delaunay image")
+        .kind(ErrorKind::Output)?;
+        report.stats = Some(stats);
+        rendered_image = Some(rendered);
+    }
+
+    if let Some((path, format)) = &pipeline.mesh_export {
+        report.timed("export mesh", || Mesh::from_drawing(&result_image).save(path, *format))
+            .with_context(|| format!("could not save the mesh {}", path.display()))
+            .kind(ErrorKind::Output)?;
+    }
+
+    report.timed("encode", || {
+        if output == Path::new(STDIO) {
+            let format = pipeline.format.unwrap_or(ImageFormat::Png);
+            encode_output(&result_image, rendered_image, &mut io::stdout().lock(), pipeline.fit, format)
+                .context("could not write output image to stdout")
+        } else {
+            let format = pipeline.format
+                .or_else(|| ImageFormat::from_path(output).ok())
+                .unwrap_or(ImageFormat::Png);
+            let file = fs::File::create(output)
+                .context("could not create output image")?;
+            encode_output(&result_image, rendered_image, &mut BufWriter::new(file), pipeline.fit, format)
+                .context("could not save output image")
+        }
+    })
+    .kind(ErrorKind::Output)
+}
+
+/// Writes the drawing, or the image already rendered from it when there is one.
+fn encode_output<W: Write>(
+    drawing: &Drawing,
+    rendered_image: Option<RgbaImage>,
+    writer: &mut W,
+    fit: Fit,
+    format: ImageFormat,
+) -> crate::Result<()> {
+    match rendered_image {
+        Some(image) => drawer::encode_image(image, writer, format),
+        None => drawing.encode_with(writer, fit, format),
+    }
+}
+
+/// Saves the image of an intermediate stage in --debug-dir, named after the output file (or the
+/// input file when writing to stdout).
+fn save_debug(pipeline: &Pipeline, report: &mut FileReport, stage: Stage, image: &RgbaImage) -> Result<(), CliError> {
+    let Some(directory) = &pipeline.debug_dir else {
+        return Ok(());
+    };
+    let name = [&report.output, &report.input].into_iter()
+        .map(Path::new)
+        .find(|path| *path != Path::new(STDIO))
+        .and_then(|path| path.file_stem())
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "stdin".to_owned());
+    let path = directory.join(format!("{}_{}.png", name, stage));
+    report.timed("debug", || image.save(&path))
+        .with_context(|| format!("could not save the debug image {}", path.display()))
+        .kind(ErrorKind::Output)
+}
+
+/// Placeholder of the frame number in --out, to write an animation as a sequence of images.
+const FRAME_PLACEHOLDER: &str = "{frame}";
+
+/// Writes the frames of an animation to `output`, as a gif or apng, or as one image per frame when
+/// `output` contains [`FRAME_PLACEHOLDER`].
+fn write_frames(
+    drawings: &[Drawing],
+    output: &Path,
+    pipeline: &Pipeline,
+    timing: Timing,
+    report: &mut FileReport,
+) -> Result<(), CliError> {
+    let pattern = output.to_string_lossy();
+    if pattern.contains(FRAME_PLACEHOLDER) {
+        let digits = drawings.len().to_string().len();
+        return report.timed("encode", || {
+            for (index, drawing) in drawings.iter().enumerate() {
+                let path = PathBuf::from(pattern.replace(FRAME_PLACEHOLDER, &format!("{:0digits$}", index + 1)));
+                let format = pipeline.format
+                    .or_else(|| ImageFormat::from_path(&path).ok())
+                    .unwrap_or(ImageFormat::Png);
+                let file = fs::File::create(&path)
+                    .with_context(|| format!("could not create the frame {}", path.display()))?;
+                drawing.encode_with(&mut BufWriter::new(file), pipeline.fit, format)
+                    .with_context(|| format!("could not save the frame {}", path.display()))?;
+            }
+            Ok(())
+        })
+        .kind(ErrorKind::Output);
+    }
+
+    // like the still images, an animation without format nor extension is a png
+    let format = match pipeline.format {
+        Some(format) => AnimationFormat::from_image_format(format),
+        None if output == Path::new(STDIO) || output.extension().is_none() => Some(AnimationFormat::Apng),
+        None => AnimationFormat::from_path(output),
+    }
+    .ok_or_else(|| anyhow!("an animation can only be written as a gif, a png (apng) or a {} image sequence", FRAME_PLACEHOLDER))
+    .kind(ErrorKind::InvalidSettings)?;
 
-    println!("{}", format_success("done (delaunay image is saved)"));
-    return Ok(())
+    report.timed("encode", || {
+        if output == Path::new(STDIO) {
+            animation::encode_frames(drawings, io::stdout().lock(), pipeline.fit, format, timing)
+                .context("could not write output animation to stdout")
+        } else {
+            let file = fs::File::create(output)
+                .context("could not create output animation")?;
+            animation::encode_frames(drawings, BufWriter::new(file), pipeline.fit, format, timing)
+                .context("could not save output animation")
+        }
+    })
+    .kind(ErrorKind::Output)
 }
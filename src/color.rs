@@ -0,0 +1,345 @@
+use std::fmt;
+use std::str::FromStr;
+
+use resvg::usvg;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+
+/// A color with an alpha channel, parsed from the CSS-like syntaxes:
+///
+/// - hex: `#rgb`, `#rgba`, `#rrggbb`, `#rrggbbaa` (the `#` is optional)
+/// - functions: `rgb(255, 0, 0)`, `rgba(100%, 0%, 0%, 0.5)`, `hsl(120, 50%, 50%)`, `hsla(120 50% 50% / 0.5)`
+/// - CSS named colors: `tomato`, `steelblue`, `transparent`, ...
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+    pub alpha: u8,
+}
+
+/// Error returned when a color can not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseColorError(String);
+
+impl fmt::Display for ParseColorError {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ParseColorError {}
+
+impl Color {
+
+    pub const fn new_rgba(red: u8, green: u8, blue: u8, alpha: u8) -> Self {
+        Self { red, green, blue, alpha }
+    }
+
+    pub const fn new_rgb(red: u8, green: u8, blue: u8) -> Self {
+        Self::new_rgba(red, green, blue, 255)
+    }
+
+    /// Returns the color without its alpha channel.
+    pub fn to_usvg(&self) -> usvg::Color {
+        usvg::Color::new_rgb(self.red, self.green, self.blue)
+    }
+
+    /// Returns the alpha channel between 0 (transparent) and 1 (opaque).
+    pub fn opacity(&self) -> f64 {
+        self.alpha as f64 / 255.0
+    }
+
+    /// Formats the color as `#rrggbb`, or `#rrggbbaa` when it is not opaque.
+    pub fn to_hex(&self) -> String {
+        if self.alpha == 255 {
+            format!("#{:02x}{:02x}{:02x}", self.red, self.green, self.blue)
+        } else {
+            format!("#{:02x}{:02x}{:02x}{:02x}", self.red, self.green, self.blue, self.alpha)
+        }
+    }
+}
+
+impl From<usvg::Color> for Color {
+
+    fn from(color: usvg::Color) -> Self {
+        Self::new_rgb(color.red, color.green, color.blue)
+    }
+}
+
+impl fmt::Display for Color {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl Serialize for Color {
+
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_hex())
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(de::Error::custom)
+    }
+}
+
+impl FromStr for Color {
+    type Err = ParseColorError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim().to_ascii_lowercase();
+        let invalid = || ParseColorError(format!(
+            "invalid color `{}` (expected #rgb, #rrggbb, #rrggbbaa, rgb(..), rgba(..), hsl(..), hsla(..) or a CSS color name)",
+            value
+        ));
+
+        if let Some((name, arguments)) = value.strip_suffix(')').and_then(|value| value.split_once('(')) {
+            let arguments = split_arguments(arguments).ok_or_else(invalid)?;
+            return match name.trim() {
+                "rgb" | "rgba" => parse_rgb(&arguments),
+                "hsl" | "hsla" => parse_hsl(&arguments),
+                _ => None,
+            }
+            .ok_or_else(invalid);
+        }
+
+        if let Some(color) = named_color(&value) {
+            return Ok(color);
+        }
+
+        parse_hex(value.trim_start_matches('#')).ok_or_else(invalid)
+    }
+}
+
+fn parse_hex(digits: &str) -> Option<Color> {
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let short = |i: usize| u8::from_str_radix(&digits[i..i + 1], 16).ok().map(|v| v * 17);
+    let long = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).ok();
+
+    match digits.len() {
+        3 => Some(Color::new_rgb(short(0)?, short(1)?, short(2)?)),
+        4 => Some(Color::new_rgba(short(0)?, short(1)?, short(2)?, short(3)?)),
+        6 => Some(Color::new_rgb(long(0)?, long(2)?, long(4)?)),
+        8 => Some(Color::new_rgba(long(0)?, long(2)?, long(4)?, long(6)?)),
+        _ => None,
+    }
+}
+
+/// Splits the arguments of `rgb(..)` and `hsl(..)`, separated by commas or by spaces with an
+/// optional `/` before the alpha.
+fn split_arguments(arguments: &str) -> Option<Vec<&str>> {
+    let arguments: Vec<&str> = if arguments.contains(',') {
+        arguments.split(',').map(str::trim).collect()
+    } else {
+        let (channels, alpha) = match arguments.split_once('/') {
+            Some((channels, alpha)) => (channels, Some(alpha.trim())),
+            None => (arguments, None),
+        };
+        channels.split_whitespace().chain(alpha).collect()
+    };
+
+    if (3..=4).contains(&arguments.len()) && arguments.iter().all(|argument| !argument.is_empty()) {
+        Some(arguments)
+    } else {
+        None
+    }
+}
+
+/// Parses a number or a percentage, `scale` is the value of `100%`.
+fn parse_number(value: &str, scale: f64) -> Option<f64> {
+    let number = match value.strip_suffix('%') {
+        Some(percentage) => percentage.trim().parse::<f64>().ok()? / 100.0 * scale,
+        None => value.parse::<f64>().ok()?,
+    };
+    number.is_finite().then_some(number)
+}
+
+fn parse_alpha(arguments: &[&str]) -> Option<u8> {
+    match arguments.get(3) {
+        Some(alpha) => Some(to_channel(parse_number(alpha, 1.0)? * 255.0)),
+        None => Some(255),
+    }
+}
+
+fn to_channel(value: f64) -> u8 {
+    value.round().clamp(0.0, 255.0) as u8
+}
+
+fn parse_rgb(arguments: &[&str]) -> Option<Color> {
+    let channel = |i: usize| parse_number(arguments[i], 255.0).map(to_channel);
+    Some(Color::new_rgba(channel(0)?, channel(1)?, channel(2)?, parse_alpha(arguments)?))
+}
+
+fn parse_hsl(arguments: &[&str]) -> Option<Color> {
+    let hue = parse_number(arguments[0].trim_end_matches("deg"), 360.0)?;
+    let saturation = parse_number(arguments[1], 1.0)?.clamp(0.0, 1.0);
+    let lightness = parse_number(arguments[2], 1.0)?.clamp(0.0, 1.0);
+    if !(arguments[1].ends_with('%') && arguments[2].ends_with('%')) {
+        return None;
+    }
+
+    let (red, green, blue) = hsl_to_rgb(hue, saturation, lightness);
+    Some(Color::new_rgba(to_channel(red * 255.0), to_channel(green * 255.0), to_channel(blue * 255.0), parse_alpha(arguments)?))
+}
+
+/// Converts a hue in degrees, a saturation and a lightness between 0 and 1 into RGB between 0 and 1.
+pub fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> (f64, f64, f64) {
+    let hue = hue.rem_euclid(360.0) / 60.0;
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (red, green, blue) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    (red + m, green + m, blue + m)
+}
+
+/// Converts RGB between 0 and 1 into a hue in degrees, a saturation and a lightness between 0 and 1.
+pub fn rgb_to_hsl(red: f64, green: f64, blue: f64) -> (f64, f64, f64) {
+    let max = red.max(green).max(blue);
+    let min = red.min(green).min(blue);
+    let lightness = (max + min) / 2.0;
+    let delta = max - min;
+    if delta == 0.0 {
+        return (0.0, 0.0, lightness);
+    }
+
+    let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+    let hue = if max == red {
+        60.0 * ((green - blue) / delta).rem_euclid(6.0)
+    } else if max == green {
+        60.0 * ((blue - red) / delta + 2.0)
+    } else {
+        60.0 * ((red - green) / delta + 4.0)
+    };
+    (hue, saturation, lightness)
+}
+
+fn named_color(name: &str) -> Option<Color> {
+    NAMED_COLORS.iter()
+        .find(|(color_name, _)| *color_name == name)
+        .map(|(_, [red, green, blue])| Color::new_rgb(*red, *green, *blue))
+        .or_else(|| (name == "transparent").then_some(Color::new_rgba(0, 0, 0, 0)))
+}
+
+/// The CSS named colors.
+const NAMED_COLORS: [(&str, [u8; 3]); 148] = [
+    ("aliceblue", [240, 248, 255]), ("antiquewhite", [250, 235, 215]), ("aqua", [0, 255, 255]),
+    ("aquamarine", [127, 255, 212]), ("azure", [240, 255, 255]), ("beige", [245, 245, 220]),
+    ("bisque", [255, 228, 196]), ("black", [0, 0, 0]), ("blanchedalmond", [255, 235, 205]),
+    ("blue", [0, 0, 255]), ("blueviolet", [138, 43, 226]), ("brown", [165, 42, 42]),
+    ("burlywood", [222, 184, 135]), ("cadetblue", [95, 158, 160]), ("chartreuse", [127, 255, 0]),
+    ("chocolate", [210, 105, 30]), ("coral", [255, 127, 80]), ("cornflowerblue", [100, 149, 237]),
+    ("cornsilk", [255, 248, 220]), ("crimson", [220, 20, 60]), ("cyan", [0, 255, 255]),
+    ("darkblue", [0, 0, 139]), ("darkcyan", [0, 139, 139]), ("darkgoldenrod", [184, 134, 11]),
+    ("darkgray", [169, 169, 169]), ("darkgreen", [0, 100, 0]), ("darkgrey", [169, 169, 169]),
+    ("darkkhaki", [189, 183, 107]), ("darkmagenta", [139, 0, 139]), ("darkolivegreen", [85, 107, 47]),
+    ("darkorange", [255, 140, 0]), ("darkorchid", [153, 50, 204]), ("darkred", [139, 0, 0]),
+    ("darksalmon", [233, 150, 122]), ("darkseagreen", [143, 188, 143]), ("darkslateblue", [72, 61, 139]),
+    ("darkslategray", [47, 79, 79]), ("darkslategrey", [47, 79, 79]), ("darkturquoise", [0, 206, 209]),
+    ("darkviolet", [148, 0, 211]), ("deeppink", [255, 20, 147]), ("deepskyblue", [0, 191, 255]),
+    ("dimgray", [105, 105, 105]), ("dimgrey", [105, 105, 105]), ("dodgerblue", [30, 144, 255]),
+    ("firebrick", [178, 34, 34]), ("floralwhite", [255, 250, 240]), ("forestgreen", [34, 139, 34]),
+    ("fuchsia", [255, 0, 255]), ("gainsboro", [220, 220, 220]), ("ghostwhite", [248, 248, 255]),
+    ("gold", [255, 215, 0]), ("goldenrod", [218, 165, 32]), ("gray", [128, 128, 128]),
+    ("green", [0, 128, 0]), ("greenyellow", [173, 255, 47]), ("grey", [128, 128, 128]),
+    ("honeydew", [240, 255, 240]), ("hotpink", [255, 105, 180]), ("indianred", [205, 92, 92]),
+    ("indigo", [75, 0, 130]), ("ivory", [255, 255, 240]), ("khaki", [240, 230, 140]),
+    ("lavender", [230, 230, 250]), ("lavenderblush", [255, 240, 245]), ("lawngreen", [124, 252, 0]),
+    ("lemonchiffon", [255, 250, 205]), ("lightblue", [173, 216, 230]), ("lightcoral", [240, 128, 128]),
+    ("lightcyan", [224, 255, 255]), ("lightgoldenrodyellow", [250, 250, 210]), ("lightgray", [211, 211, 211]),
+    ("lightgreen", [144, 238, 144]), ("lightgrey", [211, 211, 211]), ("lightpink", [255, 182, 193]),
+    ("lightsalmon", [255, 160, 122]), ("lightseagreen", [32, 178, 170]), ("lightskyblue", [135, 206, 250]),
+    ("lightslategray", [119, 136, 153]), ("lightslategrey", [119, 136, 153]), ("lightsteelblue", [176, 196, 222]),
+    ("lightyellow", [255, 255, 224]), ("lime", [0, 255, 0]), ("limegreen", [50, 205, 50]),
+    ("linen", [250, 240, 230]), ("magenta", [255, 0, 255]), ("maroon", [128, 0, 0]),
+    ("mediumaquamarine", [102, 205, 170]), ("mediumblue", [0, 0, 205]), ("mediumorchid", [186, 85, 211]),
+    ("mediumpurple", [147, 112, 219]), ("mediumseagreen", [60, 179, 113]), ("mediumslateblue", [123, 104, 238]),
+    ("mediumspringgreen", [0, 250, 154]), ("mediumturquoise", [72, 209, 204]), ("mediumvioletred", [199, 21, 133]),
+    ("midnightblue", [25, 25, 112]), ("mintcream", [245, 255, 250]), ("mistyrose", [255, 228, 225]),
+    ("moccasin", [255, 228, 181]), ("navajowhite", [255, 222, 173]), ("navy", [0, 0, 128]),
+    ("oldlace", [253, 245, 230]), ("olive", [128, 128, 0]), ("olivedrab", [107, 142, 35]),
+    ("orange", [255, 165, 0]), ("orangered", [255, 69, 0]), ("orchid", [218, 112, 214]),
+    ("palegoldenrod", [238, 232, 170]), ("palegreen", [152, 251, 152]), ("paleturquoise", [175, 238, 238]),
+    ("palevioletred", [219, 112, 147]), ("papayawhip", [255, 239, 213]), ("peachpuff", [255, 218, 185]),
+    ("peru", [205, 133, 63]), ("pink", [255, 192, 203]), ("plum", [221, 160, 221]),
+    ("powderblue", [176, 224, 230]), ("purple", [128, 0, 128]), ("rebeccapurple", [102, 51, 153]),
+    ("red", [255, 0, 0]), ("rosybrown", [188, 143, 143]), ("royalblue", [65, 105, 225]),
+    ("saddlebrown", [139, 69, 19]), ("salmon", [250, 128, 114]), ("sandybrown", [244, 164, 96]),
+    ("seagreen", [46, 139, 87]), ("seashell", [255, 245, 238]), ("sienna", [160, 82, 45]),
+    ("silver", [192, 192, 192]), ("skyblue", [135, 206, 235]), ("slateblue", [106, 90, 205]),
+    ("slategray", [112, 128, 144]), ("slategrey", [112, 128, 144]), ("snow", [255, 250, 250]),
+    ("springgreen", [0, 255, 127]), ("steelblue", [70, 130, 180]), ("tan", [210, 180, 140]),
+    ("teal", [0, 128, 128]), ("thistle", [216, 191, 216]), ("tomato", [255, 99, 71]),
+    ("turquoise", [64, 224, 208]), ("violet", [238, 130, 238]), ("wheat", [245, 222, 179]),
+    ("white", [255, 255, 255]), ("whitesmoke", [245, 245, 245]), ("yellow", [255, 255, 0]),
+    ("yellowgreen", [154, 205, 50]),
+];
+
+
+#[cfg(test)]
+mod test {
+    use super::{hsl_to_rgb, rgb_to_hsl, Color};
+
+    #[test]
+    fn test_parse_hex() {
+        assert_eq!("#fff".parse(), Ok(Color::new_rgb(255, 255, 255)));
+        assert_eq!("f008".parse(), Ok(Color::new_rgba(255, 0, 0, 136)));
+        assert_eq!("#1a2B3c".parse(), Ok(Color::new_rgb(26, 43, 60)));
+        assert_eq!("#1a2b3c80".parse(), Ok(Color::new_rgba(26, 43, 60, 128)));
+    }
+
+    #[test]
+    fn test_parse_functions() {
+        assert_eq!("rgb(255, 0, 10)".parse(), Ok(Color::new_rgb(255, 0, 10)));
+        assert_eq!("rgba(100%, 0%, 50%, 0.5)".parse(), Ok(Color::new_rgba(255, 0, 128, 128)));
+        assert_eq!("rgb(255 0 10 / 50%)".parse(), Ok(Color::new_rgba(255, 0, 10, 128)));
+        assert_eq!("hsl(0, 100%, 50%)".parse(), Ok(Color::new_rgb(255, 0, 0)));
+        assert_eq!("hsl(120deg 100% 25%)".parse(), Ok(Color::new_rgb(0, 128, 0)));
+        assert_eq!("HSLA(240, 100%, 50%, 0)".parse(), Ok(Color::new_rgba(0, 0, 255, 0)));
+    }
+
+    #[test]
+    fn test_parse_names() {
+        assert_eq!("tomato".parse(), Ok(Color::new_rgb(255, 99, 71)));
+        assert_eq!(" SteelBlue ".parse(), Ok(Color::new_rgb(70, 130, 180)));
+        assert_eq!("transparent".parse(), Ok(Color::new_rgba(0, 0, 0, 0)));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        for value in ["", "#", "#f", "#ff", "#fffff", "#fffffffff", "#ggg", "é", "rgb(1, 2)", "rgb(a, b, c)",
+                      "hsl(0, 100, 50)", "cmyk(0, 0, 0, 0)", "notacolor", "rgb(1, 2, 3"] {
+            assert!(value.parse::<Color>().is_err(), "`{}` should be invalid", value);
+        }
+    }
+
+    #[test]
+    fn test_to_hex() {
+        assert_eq!(Color::new_rgb(26, 43, 60).to_hex(), "#1a2b3c");
+        assert_eq!(Color::new_rgba(26, 43, 60, 128).to_hex(), "#1a2b3c80");
+    }
+
+    #[test]
+    fn test_hsl_roundtrip() {
+        for (red, green, blue) in [(1.0, 0.0, 0.0), (0.2, 0.4, 0.6), (0.5, 0.5, 0.5), (0.9, 0.7, 0.1)] {
+            let (hue, saturation, lightness) = rgb_to_hsl(red, green, blue);
+            let (r, g, b) = hsl_to_rgb(hue, saturation, lightness);
+            assert!((r - red).abs() < 1e-9 && (g - green).abs() < 1e-9 && (b - blue).abs() < 1e-9);
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::drawer::Drawer;
+use crate::error::{Error, Result};
+use crate::Triangulation;
+
+
+/// File format of a [`Config`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+
+    /// Guess the format from the extension of `path` (`.toml` or `.json`).
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => Ok(ConfigFormat::Toml),
+            Some(ext) if ext.eq_ignore_ascii_case("json") => Ok(ConfigFormat::Json),
+            _ => Err(Error::invalid_parameter(
+                "config",
+                format!("unknown format for `{}` (expected a .toml or .json file)", path.display()),
+            )),
+        }
+    }
+}
+
+/// Settings of the whole pipeline, shareable as a TOML or JSON file.
+///
+/// Missing fields keep their default value, so a file only needs to list the settings it changes:
+///
+/// ```toml
+/// [triangulation]
+/// max_points = 5000
+///
+/// [drawer]
+/// only_wireframe = false
+/// stroke_width = 0.0
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub triangulation: Triangulation,
+    pub drawer: Drawer,
+}
+
+impl Config {
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let format = ConfigFormat::from_path(&path)?;
+        let content = fs::read_to_string(path)?;
+        Self::parse(&content, format)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let format = ConfigFormat::from_path(&path)?;
+        fs::write(path, self.to_string(format)?)?;
+        Ok(())
+    }
+
+    pub fn parse(content: &str, format: ConfigFormat) -> Result<Self> {
+        match format {
+            ConfigFormat::Toml => toml::from_str(content).map_err(|e| Error::Config(Box::new(e))),
+            ConfigFormat::Json => serde_json::from_str(content).map_err(|e| Error::Config(Box::new(e))),
+        }
+    }
+
+    pub fn to_string(&self, format: ConfigFormat) -> Result<String> {
+        match format {
+            ConfigFormat::Toml => toml::to_string_pretty(self).map_err(|e| Error::Config(Box::new(e))),
+            ConfigFormat::Json => serde_json::to_string_pretty(self).map_err(|e| Error::Config(Box::new(e))),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use resvg::usvg;
+
+    use super::{Config, ConfigFormat};
+    use crate::color::Color;
+    use crate::palette::Palette;
+
+    #[test]
+    fn test_roundtrip() {
+        let mut config = Config::default();
+        config.triangulation.max_points = 42;
+        config.triangulation.work_size = Some(800);
+        config.drawer.stroke_color = Some(usvg::Color::new_rgb(255, 0, 16));
+
+        for palette in [Palette::KMeans(8), Palette::Colors(vec![Color::new_rgb(1, 2, 3), Color::new_rgb(4, 5, 6)])] {
+            config.drawer.palette = Some(palette);
+            for format in [ConfigFormat::Toml, ConfigFormat::Json] {
+                let content = config.to_string(format).unwrap();
+                assert_eq!(Config::parse(&content, format).unwrap(), config);
+            }
+        }
+        config.drawer.palette = None;
+
+        for format in [ConfigFormat::Toml, ConfigFormat::Json] {
+            let content = config.to_string(format).unwrap();
+            assert_eq!(Config::parse(&content, format).unwrap(), config);
+        }
+    }
+
+    #[test]
+    fn test_partial_config() {
+        let content = r##"
+            [triangulation]
+            max_points = 10
+
+            [drawer]
+            background_color = "#00ff00"
+            stroke_color = "tomato"
+        "##;
+        let config = Config::parse(content, ConfigFormat::Toml).unwrap();
+
+        assert_eq!(config.triangulation.max_points, 10);
+        assert_eq!(config.triangulation.blur_factor, Config::default().triangulation.blur_factor);
+        assert_eq!(config.drawer.background_color, Some(usvg::Color::new_rgb(0, 255, 0)));
+        assert_eq!(config.drawer.stroke_color, Some(usvg::Color::new_rgb(255, 99, 71)));
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(Config::parse("[triangulation]\nmax_point = 10", ConfigFormat::Toml).is_err());
+        assert!(Config::parse(r##"{"drawer": {"stroke_color": "#12"}}"##, ConfigFormat::Json).is_err());
+        assert!(Config::parse(r##"{"drawer": {"stroke_color": "#ff000080"}}"##, ConfigFormat::Json).is_err());
+        assert!(ConfigFormat::from_path("style.yaml").is_err());
+        assert_eq!(ConfigFormat::from_path("style.TOML").unwrap(), ConfigFormat::Toml);
+    }
+}
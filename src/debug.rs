@@ -0,0 +1,122 @@
+use std::fmt;
+
+use image::{Rgba, RgbaImage};
+use resvg::usvg;
+
+use crate::delaunay::{Point, Triangle};
+use crate::drawer::{Drawable, Drawer, Fit};
+use crate::error::Result;
+
+
+/// An intermediate stage of the pipeline, saved as an image to tune the parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// the source image blurred by the blur filter
+    Blur,
+    /// the edges found by the sobel filter
+    Edge,
+    /// the pixels where the points are sampled, in white
+    Mask,
+    /// the sampled points over the source image
+    Points,
+    /// the edges of the triangles
+    Wireframe,
+}
+
+impl Stage {
+
+    pub const ALL: [Stage; 5] = [Stage::Blur, Stage::Edge, Stage::Mask, Stage::Points, Stage::Wireframe];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::Blur => "blur",
+            Stage::Edge => "edge",
+            Stage::Mask => "mask",
+            Stage::Points => "points",
+            Stage::Wireframe => "wireframe",
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+/// Draws the `points` in white over a black image.
+pub fn mask_image(width: u32, height: u32, points: &[Point]) -> RgbaImage {
+    let mut image = RgbaImage::from_pixel(width, height, Rgba::from([0, 0, 0, 255]));
+    for point in points {
+        if point.x >= 0.0 && point.y >= 0.0 && (point.x as u32) < width && (point.y as u32) < height {
+            image.put_pixel(point.x as u32, point.y as u32, Rgba::from([255, 255, 255, 255]));
+        }
+    }
+    image
+}
+
+/// Draws the `points` as red dots over the source image, darkened so they stand out.
+pub fn points_overlay(source_image: &RgbaImage, points: &[Point]) -> RgbaImage {
+    let mut image = RgbaImage::from_fn(source_image.width(), source_image.height(), |x, y| {
+        let pixel = source_image.get_pixel(x, y);
+        Rgba::from([pixel[0] / 2, pixel[1] / 2, pixel[2] / 2, 255])
+    });
+    let (width, height) = (image.width() as i64, image.height() as i64);
+    for point in points {
+        let (x, y) = (point.x.round() as i64, point.y.round() as i64);
+        for (dx, dy) in [(0, 0), (-1, 0), (1, 0), (0, -1), (0, 1)] {
+            let (x, y) = (x + dx, y + dy);
+            if (0..width).contains(&x) && (0..height).contains(&y) {
+                image.put_pixel(x as u32, y as u32, Rgba::from([255, 0, 0, 255]));
+            }
+        }
+    }
+    image
+}
+
+/// Draws the edges of the triangles in black over a white image of the size of the source image.
+pub fn wireframe(source_image: &RgbaImage, triangles: &[Triangle]) -> Result<RgbaImage> {
+    let drawer = Drawer {
+        only_wireframe: true,
+        stroke_width: 1.0,
+        stroke_color: Some(usvg::Color::black()),
+        stroke_opacity: 1.0,
+        with_background: true,
+        background_color: Some(usvg::Color::white()),
+        background_opacity: 1.0,
+        ..Default::default()
+    };
+    // every triangle is drawn, even over the transparent areas of the source image
+    let opaque = RgbaImage::from_pixel(source_image.width(), source_image.height(), Rgba::from([255, 255, 255, 255]));
+    drawer.draw(opaque, triangles.to_vec())?.to_image_with(Fit::Original)
+}
+
+
+#[cfg(test)]
+mod test {
+    use image::{Rgba, RgbaImage};
+
+    use super::{mask_image, points_overlay, wireframe, Stage};
+    use crate::delaunay::{Point, Triangle};
+
+    #[test]
+    fn test_images() {
+        let points = [Point { x: 2.0, y: 1.0 }, Point { x: 9.0, y: 9.0 }, Point { x: 20.0, y: 1.0 }];
+        let mask = mask_image(10, 10, &points);
+        assert_eq!(mask.pixels().filter(|pixel| pixel.0 == [255, 255, 255, 255]).count(), 2);
+
+        let source = RgbaImage::from_pixel(10, 10, Rgba::from([200, 100, 50, 255]));
+        let overlay = points_overlay(&source, &points);
+        assert_eq!(overlay.get_pixel(2, 2).0, [255, 0, 0, 255]);
+        assert_eq!(overlay.get_pixel(5, 5).0, [100, 50, 25, 255]);
+
+        let triangle = Triangle::new(Point { x: 0.0, y: 0.0 }, Point { x: 10.0, y: 0.0 }, Point { x: 0.0, y: 10.0 });
+        let image = wireframe(&RgbaImage::new(10, 10), &[triangle]).unwrap();
+        assert_eq!(image.dimensions(), (10, 10));
+        assert!(image.get_pixel(4, 5)[0] < 128);
+        assert_eq!(image.get_pixel(8, 8).0, [255, 255, 255, 255]);
+
+        assert_eq!(Stage::ALL.map(|stage| stage.to_string()), ["blur", "edge", "mask", "points", "wireframe"]);
+    }
+}
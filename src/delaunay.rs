@@ -74,11 +74,10 @@ pub struct Triangle {
 impl PartialEq for Triangle {
     
     fn eq(&self, other: &Self) -> bool {
-        other.vertex.iter().map(|other_vertex| {
+        other.vertex.iter().all(|other_vertex| {
             self.vertex.iter()
                 .any(|vertex| {other_vertex == vertex })
         })
-        .fold(true, |acc, is_equal|{ acc && is_equal })
     }
 }
 
@@ -98,10 +97,10 @@ impl Triangle {
         
         let m = p1.x * p1.x - p0.x * p0.x + p1.y * p1.y - p0.y * p0.y;
         let u = p2.x * p2.x - p0.x * p0.x + p2.y * p2.y - p0.y * p0.y;
-        let s = 1.0 / (2.0 * f64::from(ax * by - ay * bx));
+        let s = 1.0 / (2.0 * (ax * by - ay * bx));
         
-        let center_x = f64::from((p2.y - p0.y) * m + (p0.y - p1.y) * u) * s;
-        let center_y = f64::from((p0.x - p2.x) * m + (p1.x - p0.x) * u) * s;
+        let center_x = ((p2.y - p0.y) * m + (p0.y - p1.y) * u) * s;
+        let center_y = ((p0.x - p2.x) * m + (p1.x - p0.x) * u) * s;
     
 
        let center = Point{x: center_x, y: center_y};
@@ -126,6 +125,11 @@ impl Triangle {
     pub fn vertex(&self) ->  [Point; 3] {
         self.vertex
     }
+
+    pub fn area(&self) -> f64 {
+        let [p0, p1, p2] = self.vertex;
+        ((p1.x - p0.x) * (p2.y - p0.y) - (p2.x - p0.x) * (p1.y - p0.y)).abs() / 2.0
+    }
 }
 
 
@@ -161,7 +165,7 @@ impl Delaunay {
 
     
 
-        for (_, p) in points.iter().enumerate() {
+        for p in points.iter() {
             let (x, y) = (p.x, p.y);
             let triangles = &self.triangles;
             let mut edges = Vec::new();
@@ -265,7 +269,7 @@ mod test {
 
         let triangles = delanay.triangles();
 
-        let expected_triangles = vec![
+        let expected_triangles = [
             Triangle::new(a, d, e),
             Triangle::new(a, b, e),
             Triangle::new(d, e, f),
@@ -277,7 +281,7 @@ mod test {
         assert_eq!(expected_triangles.len(), triangles.len());
 
         expected_triangles.iter().for_each(|t| {
-            let is_present = triangles.iter().any(|triangle|  *triangle == *t);
+            let is_present = triangles.contains(t);
             assert!(is_present);
         })
     }
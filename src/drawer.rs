@@ -1,91 +1,318 @@
 
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{Cursor, Write};
 use std::rc::Rc;
+use std::str::FromStr;
 
 
-use image::{ImageBuffer, Rgba};
+use image::{DynamicImage, ImageBuffer, ImageFormat, Rgba, RgbaImage};
 use resvg::{usvg::{self, NodeExt}, tiny_skia};
+use serde::{Deserialize, Serialize};
 
-use crate::delaunay::Triangle;
+use crate::delaunay::{Point, Triangle};
+use crate::adjust::ColorAdjust;
+use crate::error::{Error, Result};
+use crate::palette::{Palette, Quantizer};
+use crate::raster::Rasterizer;
+use crate::Edges;
 
+/// How the drawing is sized when it is rendered.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fit {
+    /// keep the size of the source image
+    Original,
+    /// scale to the given width, preserving the aspect ratio
+    Width(u32),
+    /// scale to the given height, preserving the aspect ratio
+    Height(u32),
+    /// scale to fit inside the given size, preserving the aspect ratio
+    Size(u32, u32),
+    /// scale to exactly the given size, ignoring the aspect ratio
+    Stretch(u32, u32),
+    /// scale by a factor
+    Zoom(f64),
+}
+
+impl Fit {
+
+    /// Returns the size in pixel of a drawing of `width` x `height` once fitted.
+    pub fn fit_to(&self, width: f64, height: f64) -> Option<(u32, u32)> {
+        let (fit_width, fit_height) = match *self {
+            Fit::Original => (width, height),
+            Fit::Width(w) => (w as f64, (w as f64 * height / width).ceil()),
+            Fit::Height(h) => ((h as f64 * width / height).ceil(), h as f64),
+            Fit::Size(w, h) => {
+                let ratio = (w as f64 / width).min(h as f64 / height);
+                ((width * ratio).ceil(), (height * ratio).ceil())
+            },
+            Fit::Stretch(w, h) => (w as f64, h as f64),
+            Fit::Zoom(factor) => ((width * factor).ceil(), (height * factor).ceil()),
+        };
 
-#[derive(Debug)]
-pub enum DrawingError {
-    Encoding,  
-    Rendering, 
+        if fit_width.is_finite() && fit_height.is_finite() && fit_width >= 1.0 && fit_height >= 1.0 {
+            Some((fit_width as u32, fit_height as u32))
+        } else {
+            None
+        }
+    }
 }
 
-pub struct Drawing {
-    svg_tree: usvg::Tree,
+/// Color and opacity of the fill or the stroke of a [`Shape`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Paint {
+    pub color: usvg::Color,
+    /// between 0 (transparent) and 1 (opaque)
+    pub opacity: f64,
 }
 
+impl Paint {
 
-impl Drawing {
+    fn to_skia_color(self) -> tiny_skia::Color {
+        let mut color = tiny_skia::Color::from_rgba8(self.color.red, self.color.green, self.color.blue, 255);
+        color.set_alpha(self.opacity as f32);
+        color
+    }
 
-    pub fn render(&self) -> Result<tiny_skia::Pixmap, DrawingError> {
-        let pixmap_size: usvg::ScreenSize = self.svg_tree.size.to_screen_size();
-        let mut pixmap = tiny_skia::Pixmap::new(pixmap_size.width(), pixmap_size.height())
-            .ok_or(DrawingError::Rendering)?;
+    fn rgb(self) -> [u8; 3] {
+        [self.color.red, self.color.green, self.color.blue]
+    }
+}
 
-        let rendering = resvg::render(
-            &self.svg_tree,
-            resvg::FitTo::Original,
-            tiny_skia::Transform::default(),
-            pixmap.as_mut(),
-        );
+/// Stroke of a [`Shape`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stroke {
+    pub paint: Paint,
+    pub width: f64,
+}
 
-        if rendering.is_some() {
-            Ok(pixmap)
-        } else {
-            Err(DrawingError::Rendering)
+/// A triangle of a [`Drawing`], filled and/or stroked.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shape {
+    pub vertex: [Point; 3],
+    pub fill: Option<Paint>,
+    pub stroke: Option<Stroke>,
+}
+
+/// A circle drawn at a vertex of the triangles of a [`Drawing`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Marker {
+    pub center: Point,
+    pub radius: f64,
+    pub paint: Paint,
+}
+
+/// Rasterizer used to render a [`Drawing`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RenderBackend {
+    /// builds a svg tree with a node per triangle, rasterized by resvg
+    #[default]
+    Svg,
+    /// fills the triangles directly into the pixmap with a scanline rasterizer, faster on large meshes
+    Raster,
+}
+
+impl fmt::Display for RenderBackend {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderBackend::Svg => f.pad("svg"),
+            RenderBackend::Raster => f.pad("raster"),
         }
+    }
+}
 
-    
+impl FromStr for RenderBackend {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "svg" => Ok(RenderBackend::Svg),
+            "raster" => Ok(RenderBackend::Raster),
+            _ => Err(format!("unknown render backend `{}` (expected svg or raster)", value)),
+        }
     }
+}
+
+/// How the radius of the vertex markers is chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VertexSizing {
+    /// every marker has the `vertex_radius` of the drawer
+    #[default]
+    Fixed,
+    /// the radius grows with the strength of the edges of the source image under the vertex, from
+    /// a quarter of `vertex_radius` on flat areas to `vertex_radius` on the strongest edges
+    Edge,
+}
 
-    pub fn save_as_png(&self, filepath: &str) -> Result<(), DrawingError>{
-        let pixmap = self.render()?;
-        pixmap.save_png(filepath).map_err(|_|  DrawingError::Encoding)
+impl fmt::Display for VertexSizing {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VertexSizing::Fixed => f.pad("fixed"),
+            VertexSizing::Edge => f.pad("edge"),
+        }
     }
-    
 }
 
+impl FromStr for VertexSizing {
+    type Err = String;
 
-pub trait Drawable {
-  fn draw(&self, source_image: ImageBuffer<Rgba<u8>, Vec<u8>>, triangles: Vec<Triangle>) -> Option<Drawing>; 
-    
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "fixed" => Ok(VertexSizing::Fixed),
+            "edge" => Ok(VertexSizing::Edge),
+            _ => Err(format!("unknown vertex sizing `{}` (expected fixed or edge)", value)),
+        }
+    }
 }
 
-pub struct  Drawer {
-    pub only_wireframe: bool, 
-    pub stroke_width: f64, 
-    pub stroke_color: Option<usvg::Color>,
-    pub with_background: bool, 
-    pub background_color: Option<usvg::Color>
+/// The triangles generated by a [`Drawable`], ready to be rendered.
+pub struct Drawing {
+    width: f64,
+    height: f64,
+    background: Option<Paint>,
+    shapes: Vec<Shape>,
+    markers: Vec<Marker>,
+    backend: RenderBackend,
+    seamless: bool,
 }
 
-impl Default for Drawer {
-    fn default() -> Self {
-        Self { 
-            only_wireframe: true, 
-            stroke_color: None, 
-            stroke_width: 0.1, 
-            with_background: false, 
-            background_color: None 
+impl Drawing {
+
+    pub fn new(width: f64, height: f64, background: Option<Paint>, shapes: Vec<Shape>) -> Self {
+        Self { width, height, background, shapes, markers: vec![], backend: RenderBackend::default(), seamless: false }
+    }
+
+    /// Draws the `markers` over the triangles.
+    pub fn with_markers(mut self, markers: Vec<Marker>) -> Self {
+        self.markers = markers;
+        self
+    }
+
+    pub fn markers(&self) -> &[Marker] {
+        &self.markers
+    }
+
+    /// Selects the rasterizer used by the render methods.
+    pub fn with_backend(mut self, backend: RenderBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    pub fn backend(&self) -> RenderBackend {
+        self.backend
+    }
+
+    /// Renders the edges shared by two filled triangles without antialiasing, so that no background
+    /// shows through the seams of the mesh. The raster backend keeps the antialiasing of the outer
+    /// edges of the mesh, the svg backend can only turn it off for a whole triangle so its fills are
+    /// fully aliased (the strokes keep their antialiasing).
+    pub fn with_seamless(mut self, seamless: bool) -> Self {
+        self.seamless = seamless;
+        self
+    }
+
+    pub fn seamless(&self) -> bool {
+        self.seamless
+    }
+
+    /// Returns the size of the drawing before any fit.
+    pub fn size(&self) -> (f64, f64) {
+        (self.width, self.height)
+    }
+
+    pub fn background(&self) -> Option<Paint> {
+        self.background
+    }
+
+    pub fn shapes(&self) -> &[Shape] {
+        &self.shapes
+    }
+
+    pub fn render(&self) -> Result<tiny_skia::Pixmap> {
+        self.render_with(Fit::Original)
+    }
+
+    pub fn render_with(&self, fit: Fit) -> Result<tiny_skia::Pixmap> {
+        let (width, height) = fit.fit_to(self.width, self.height)
+            .ok_or_else(|| Error::invalid_parameter("fit", format!("{:?} gives an empty image", fit)))?;
+        let mut pixmap = tiny_skia::Pixmap::new(width, height)
+            .ok_or_else(|| Error::Rendering(format!("could not allocate a {}x{} image", width, height)))?;
+
+        // the drawing is always rendered at its original size, the scaling is done by the transform
+        // so that non uniform fit (stretch) are also supported
+        let transform = tiny_skia::Transform::from_scale(
+            (width as f64 / self.width) as f32,
+            (height as f64 / self.height) as f32,
+        );
+
+        match self.backend {
+            RenderBackend::Svg => self.render_svg(&mut pixmap, transform)?,
+            RenderBackend::Raster => self.render_raster(&mut pixmap, transform),
         }
+        Ok(pixmap)
     }
-}
 
-impl Drawable for Drawer {
-    fn draw(&self, source_image: ImageBuffer<Rgba<u8>, Vec<u8>>, triangles: Vec<Triangle>) -> Option<Drawing> {
+    fn render_svg(&self, pixmap: &mut tiny_skia::Pixmap, transform: tiny_skia::Transform) -> Result<()> {
+        resvg::render(&self.to_svg_tree()?, resvg::FitTo::Original, transform, pixmap.as_mut())
+            .ok_or_else(|| Error::Rendering("the svg tree could not be rasterized".to_owned()))
+    }
 
-        if triangles.len() == 0 {
-            return None;
+    fn render_raster(&self, pixmap: &mut tiny_skia::Pixmap, transform: tiny_skia::Transform) {
+        // the background covers the whole pixmap whatever the fit
+        if let Some(background) = self.background {
+            pixmap.fill(background.to_skia_color());
         }
 
+        // number of filled triangles along each edge
+        let edge_key = |from: Point, to: Point| {
+            let (from, to) = if (from.x, from.y) <= (to.x, to.y) { (from, to) } else { (to, from) };
+            [from.x.to_bits(), from.y.to_bits(), to.x.to_bits(), to.y.to_bits()]
+        };
+        let mut edges: HashMap<[u64; 4], u32> = HashMap::new();
+        if self.seamless {
+            for shape in self.shapes.iter().filter(|shape| shape.fill.is_some()) {
+                for i in 0..3 {
+                    *edges.entry(edge_key(shape.vertex[i], shape.vertex[(i + 1) % 3])).or_default() += 1;
+                }
+            }
+        }
 
-        let (width, height) = source_image.dimensions();
-        let size = usvg::Size::new(width as f64, height as f64)?;
+        let (scale_x, scale_y) = (transform.sx as f64, transform.sy as f64);
+        let mut rasterizer = Rasterizer::new(pixmap);
+        for shape in &self.shapes {
+            let vertex = shape.vertex.map(|point| Point { x: point.x * scale_x, y: point.y * scale_y });
+            if let Some(fill) = shape.fill {
+                let shared = [0, 1, 2].map(|i| {
+                    edges.get(&edge_key(shape.vertex[i], shape.vertex[(i + 1) % 3])).is_some_and(|count| *count > 1)
+                });
+                rasterizer.fill_triangle(vertex, shared, fill.rgb(), fill.opacity);
+            }
+            if let Some(stroke) = shape.stroke {
+                // like in svg, the width of the stroke is scaled along the normal of each edge
+                let widths = [0, 1, 2].map(|i| {
+                    let (from, to) = (shape.vertex[i], shape.vertex[(i + 1) % 3]);
+                    let (dx, dy) = (to.x - from.x, to.y - from.y);
+                    let normal = (dy / scale_x).hypot(dx / scale_y);
+                    if normal > 0.0 { stroke.width * dx.hypot(dy) / normal } else { stroke.width * scale_x.min(scale_y) }
+                });
+                rasterizer.stroke_triangle(vertex, widths, stroke.paint.rgb(), stroke.paint.opacity);
+            }
+        }
+        for marker in &self.markers {
+            let center = Point { x: marker.center.x * scale_x, y: marker.center.y * scale_y };
+            let radii = (marker.radius * scale_x, marker.radius * scale_y);
+            rasterizer.fill_ellipse(center, radii, marker.paint.rgb(), marker.paint.opacity);
+        }
+    }
 
+    /// Builds the svg tree of the drawing, with a path node per triangle.
+    fn to_svg_tree(&self) -> Result<usvg::Tree> {
+        let size = usvg::Size::new(self.width, self.height)
+            .ok_or_else(|| Error::DegenerateGeometry(format!("the drawing is empty ({}x{})", self.width, self.height)))?;
         let tree = usvg::Tree {
             size,
             view_box: usvg::ViewBox {
@@ -95,50 +322,33 @@ impl Drawable for Drawer {
             root: usvg::Node::new(usvg::NodeKind::Group(usvg::Group::default())),
         };
 
-        // drawing background
-        if self.with_background {
-            let fill_background = usvg::Fill::from_paint(
-                usvg::Paint::Color(self.background_color.unwrap_or(usvg::Color::white()))
-            );
-
-            let background =  usvg::Rect::new(0.0, 0.0, width as f64, height as f64)?;
+        let svg_fill = |paint: Paint| usvg::Fill {
+            opacity: usvg::Opacity::new_clamped(paint.opacity),
+            ..usvg::Fill::from_paint(usvg::Paint::Color(paint.color))
+        };
 
+        // drawing background
+        if let Some(background) = self.background {
             let node_background = usvg::NodeKind::Path(usvg::Path {
-                fill: Some(fill_background),
-                data: Rc::new(usvg::PathData::from_rect(background)),
+                fill: Some(svg_fill(background)),
+                data: Rc::new(usvg::PathData::from_rect(size.to_rect(0.0, 0.0))),
                 ..usvg::Path::default()
             });
             tree.root.append_kind(node_background);
         }
 
-
         // drawing triangle
-        for triangle in triangles {
-
-            let center = triangle.center();
-            let pixel = source_image.get_pixel(center.x as u32, center.y as u32);
-            let color =  usvg::Color::new_rgb(pixel[0], pixel[1], pixel[2]);
-            let vertex = triangle.vertex();
-
-            let stroke_triangle = if self.stroke_width > 0.0 {
-                let stroke_color = self.stroke_color.unwrap_or(usvg::Color::black());
+        for shape in &self.shapes {
+            let vertex = shape.vertex;
+            let stroke_triangle = shape.stroke.and_then(|stroke| {
                 Some(usvg::Stroke {
-                    paint: usvg::Paint::Color(stroke_color),
-                    width: usvg::NonZeroPositiveF64::new(self.stroke_width).unwrap(),
+                    paint: usvg::Paint::Color(stroke.paint.color),
+                    opacity: usvg::Opacity::new_clamped(stroke.paint.opacity),
+                    width: usvg::NonZeroPositiveF64::new(stroke.width)?,
                     linejoin: usvg::LineJoin::Round,
                     ..Default::default()
                 })
-            } else {
-                None
-            };
-
-            let fill_triangle =  if !self.only_wireframe  {
-                Some(
-                    usvg::Fill::from_paint(usvg::Paint::Color(color))
-                )
-            } else {
-                None
-            };
+            });
 
             let mut path_triangle = usvg::PathData::new();
             path_triangle.push_move_to(vertex[0].x, vertex[0].y);
@@ -147,19 +357,1027 @@ impl Drawable for Drawer {
             path_triangle.push_line_to(vertex[0].x, vertex[0].y);
             path_triangle.push_close_path();
 
+            let path_triangle = Rc::new(path_triangle);
+            if self.seamless && shape.fill.is_some() {
+                // without antialiasing the fills of adjacent triangles meet exactly, the stroke is
+                // drawn by a second node to keep its antialiasing. The rendering mode applies to the
+                // whole path, so the outer edges of the mesh are aliased too.
+                let node_fill = usvg::NodeKind::Path(usvg::Path {
+                    fill: shape.fill.map(svg_fill),
+                    rendering_mode: usvg::ShapeRendering::CrispEdges,
+                    data: Rc::clone(&path_triangle),
+                    ..usvg::Path::default()
+                });
+                tree.root.append_kind(node_fill);
+                if let Some(stroke) = stroke_triangle {
+                    let node_stroke = usvg::NodeKind::Path(usvg::Path {
+                        stroke: Some(stroke),
+                        data: path_triangle,
+                        ..usvg::Path::default()
+                    });
+                    tree.root.append_kind(node_stroke);
+                }
+                continue;
+            }
+
             let node_triangle = usvg::NodeKind::Path(usvg::Path {
-                fill: fill_triangle,
+                fill: shape.fill.map(svg_fill),
                 stroke: stroke_triangle,
-                data: Rc::new(path_triangle),
+                data: path_triangle,
                 ..usvg::Path::default()
             });
-    
             tree.root.append_kind(node_triangle);
+        }
 
+        // drawing vertex markers, a circle is made of four cubic curves
+        const KAPPA: f64 = 0.552_284_749_831;
+        for marker in &self.markers {
+            let (Point { x, y }, radius) = (marker.center, marker.radius);
+            let handle = radius * KAPPA;
+            let mut path_circle = usvg::PathData::new();
+            path_circle.push_move_to(x + radius, y);
+            path_circle.push_curve_to(x + radius, y + handle, x + handle, y + radius, x, y + radius);
+            path_circle.push_curve_to(x - handle, y + radius, x - radius, y + handle, x - radius, y);
+            path_circle.push_curve_to(x - radius, y - handle, x - handle, y - radius, x, y - radius);
+            path_circle.push_curve_to(x + handle, y - radius, x + radius, y - handle, x + radius, y);
+            path_circle.push_close_path();
+
+            let node_marker = usvg::NodeKind::Path(usvg::Path {
+                fill: Some(svg_fill(marker.paint)),
+                data: Rc::new(path_circle),
+                ..usvg::Path::default()
+            });
+            tree.root.append_kind(node_marker);
         }
+
+        Ok(tree)
+    }
+
+    /// Renders the drawing into an image with straight (not premultiplied) alpha.
+    pub fn to_image_with(&self, fit: Fit) -> Result<RgbaImage> {
+        let pixmap = self.render_with(fit)?;
+        let pixels = pixmap.pixels()
+            .iter()
+            .flat_map(|pixel| {
+                let color = pixel.demultiply();
+                [color.red(), color.green(), color.blue(), color.alpha()]
+            })
+            .collect();
+
+        RgbaImage::from_raw(pixmap.width(), pixmap.height(), pixels)
+            .ok_or_else(|| Error::Rendering("the rendered image has an invalid size".to_owned()))
+    }
+
+    /// Renders the drawing and writes it to `writer` encoded in `format`.
+    pub fn encode_with<W: Write>(&self, writer: &mut W, fit: Fit, format: ImageFormat) -> Result<()> {
+        let bytes = if format == ImageFormat::Png {
+            self.render_with(fit)?
+                .encode_png()
+                .map_err(|e| Error::Encoding(Box::new(e)))?
+        } else {
+            return encode_image(self.to_image_with(fit)?, writer, format);
+        };
+
+        writer.write_all(&bytes)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn save_as_png(&self, filepath: &str) -> Result<()>{
+        self.save_as_png_with(filepath, Fit::Original)
+    }
+
+    pub fn save_as_png_with(&self, filepath: &str, fit: Fit) -> Result<()>{
+        let pixmap = self.render_with(fit)?;
+        pixmap.save_png(filepath).map_err(|e| Error::Encoding(Box::new(e)))
+    }
+    
+}
+
+/// Writes an image already rendered, see [`Drawing::to_image_with`], in the given format.
+pub fn encode_image<W: Write>(image: RgbaImage, writer: &mut W, format: ImageFormat) -> Result<()> {
+    // the encoders of the image crate need a seekable writer
+    let mut buffer = Cursor::new(vec![]);
+    DynamicImage::ImageRgba8(image).write_to(&mut buffer, format)?;
+
+    writer.write_all(buffer.get_ref())?;
+    writer.flush()?;
+    Ok(())
+}
+
+
+pub trait Drawable {
+  fn draw(&self, source_image: ImageBuffer<Rgba<u8>, Vec<u8>>, triangles: Vec<Triangle>) -> Result<Drawing>; 
     
-        Some(Drawing { svg_tree: tree})
+}
+
+/// How the color of the triangle strokes is chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StrokeMode {
+    /// the `stroke_color` of the drawer, black by default
+    #[default]
+    Fixed,
+    /// the fill color of the triangle, which hides the hairline gaps between the triangles
+    Fill,
+    /// the fill color darkened by a factor between 0 (unchanged) and 1 (black)
+    Darker(f64),
+    /// the fill color lightened by a factor between 0 (unchanged) and 1 (white)
+    Lighter(f64),
+    /// the color of the source image sampled along the edges of the triangle
+    Sampled,
+}
+
+impl StrokeMode {
+
+    /// Factor used by `darker` and `lighter` when none is given.
+    pub const DEFAULT_FACTOR: f64 = 0.3;
+
+    /// Checks that the factor is in its valid range.
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            StrokeMode::Darker(factor) | StrokeMode::Lighter(factor) if !(0.0..=1.0).contains(factor) => {
+                Err(Error::invalid_parameter(
+                    "stroke_mode",
+                    format!("the factor must be between 0 and 1, got {}", factor),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Derives the stroke color from the fill color, for the `fill`, `darker` and `lighter` modes.
+    fn shade_fill(&self, fill: Rgba<u8>) -> Rgba<u8> {
+        let shade = |channel: u8| match self {
+            StrokeMode::Darker(factor) => channel as f64 * (1.0 - factor),
+            StrokeMode::Lighter(factor) => channel as f64 + (255.0 - channel as f64) * factor,
+            _ => channel as f64,
+        };
+        let [red, green, blue] = [0, 1, 2].map(|i| shade(fill[i]).round().clamp(0.0, 255.0) as u8);
+        Rgba::from([red, green, blue, fill[3]])
+    }
+}
+
+impl fmt::Display for StrokeMode {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StrokeMode::Fixed => write!(f, "fixed"),
+            StrokeMode::Fill => write!(f, "fill"),
+            StrokeMode::Darker(factor) => write!(f, "darker:{}", factor),
+            StrokeMode::Lighter(factor) => write!(f, "lighter:{}", factor),
+            StrokeMode::Sampled => write!(f, "sampled"),
+        }
+    }
+}
+
+impl FromStr for StrokeMode {
+    type Err = String;
+
+    /// Parses `fixed`, `fill`, `sampled`, `darker[:FACTOR]` or `lighter[:FACTOR]`.
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        let (name, factor) = match value.split_once(':') {
+            Some((name, factor)) => {
+                let factor = factor.parse::<f64>().map_err(|_| format!("invalid factor `{}`", factor))?;
+                (name, Some(factor))
+            }
+            None => (value, None),
+        };
+
+        let mode = match (name.to_ascii_lowercase().as_str(), factor) {
+            ("fixed", None) => StrokeMode::Fixed,
+            ("fill", None) => StrokeMode::Fill,
+            ("sampled", None) => StrokeMode::Sampled,
+            ("darker", factor) => StrokeMode::Darker(factor.unwrap_or(Self::DEFAULT_FACTOR)),
+            ("lighter", factor) => StrokeMode::Lighter(factor.unwrap_or(Self::DEFAULT_FACTOR)),
+            _ => return Err(format!(
+                "unknown stroke mode `{}` (expected fixed, fill, sampled, darker[:FACTOR] or lighter[:FACTOR])", value
+            )),
+        };
+        mode.validate().map_err(|e| e.to_string())?;
+        Ok(mode)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct  Drawer {
+    pub only_wireframe: bool, 
+    /// only draw the vertex markers, without filling nor stroking the triangles
+    pub only_points: bool,
+    pub stroke_width: f64, 
+    #[serde(with = "hex_color", skip_serializing_if = "Option::is_none")]
+    pub stroke_color: Option<usvg::Color>,
+    /// opacity of the stroke, between 0 (transparent) and 1 (opaque)
+    pub stroke_opacity: f64,
+    /// how the stroke color is chosen, `stroke_color` is only used by [`StrokeMode::Fixed`]
+    pub stroke_mode: StrokeMode,
+    /// rasterizer of the drawings
+    pub backend: RenderBackend,
+    /// fill the gaps the antialiasing leaves between adjacent triangles, see [`Drawing::with_seamless`]
+    pub seamless: bool,
+    /// radius of the circle drawn at each vertex, 0 draws none
+    pub vertex_radius: f64,
+    /// color of the vertex markers, the color of the source image under the vertex when unset
+    #[serde(with = "hex_color", skip_serializing_if = "Option::is_none")]
+    pub vertex_color: Option<usvg::Color>,
+    /// opacity of the vertex markers, between 0 (transparent) and 1 (opaque)
+    pub vertex_opacity: f64,
+    /// how the radius of each vertex marker is chosen
+    pub vertex_sizing: VertexSizing,
+    pub with_background: bool, 
+    #[serde(with = "hex_color", skip_serializing_if = "Option::is_none")]
+    pub background_color: Option<usvg::Color>,
+    /// opacity of the background, between 0 (transparent) and 1 (opaque)
+    pub background_opacity: f64,
+    /// adjustments applied to the fill colors
+    #[serde(skip_serializing_if = "ColorAdjust::is_identity")]
+    pub adjust: ColorAdjust,
+    /// when set, the fill colors are snapped to the nearest color of the palette
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub palette: Option<Palette>,
+}
+
+/// (De)serialize the colors of the [`Drawer`], written as `#rrggbb` and read with any syntax
+/// supported by [`Color`](crate::color::Color) as long as the color is opaque.
+mod hex_color {
+    use resvg::usvg;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use crate::color::Color;
+
+    pub fn serialize<S: Serializer>(color: &Option<usvg::Color>, serializer: S) -> Result<S::Ok, S::Error> {
+        match color {
+            Some(color) => serializer.serialize_str(&Color::from(*color).to_hex()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<usvg::Color>, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        let color: Color = value.parse().map_err(D::Error::custom)?;
+        if color.alpha != 255 {
+            return Err(D::Error::custom(format!(
+                "color `{}` is not opaque, set the transparency with the opacity fields", value
+            )));
+        }
+        Ok(Some(color.to_usvg()))
+    }
+}
+
+impl Default for Drawer {
+    fn default() -> Self {
+        Self { 
+            only_wireframe: false, 
+            only_points: false,
+            stroke_color: None, 
+            stroke_width: 0.1, 
+            stroke_opacity: 1.0,
+            stroke_mode: StrokeMode::Fixed,
+            backend: RenderBackend::Svg,
+            seamless: false,
+            vertex_radius: 0.0,
+            vertex_color: None,
+            vertex_opacity: 1.0,
+            vertex_sizing: VertexSizing::Fixed,
+            with_background: true, 
+            background_color: None,
+            background_opacity: 1.0,
+            adjust: ColorAdjust::default(),
+            palette: None,
+        }
+    }
+}
+
+/// Builder for [`Drawer`], the parameters are checked by [`DrawerBuilder::build`].
+#[derive(Default)]
+pub struct DrawerBuilder {
+    drawer: Drawer,
+}
+
+impl From<Drawer> for DrawerBuilder {
+
+    fn from(drawer: Drawer) -> Self {
+        Self { drawer }
     }
 }
 
+impl DrawerBuilder {
 
+    pub fn only_wireframe(mut self, only_wireframe: bool) -> Self {
+        self.drawer.only_wireframe = only_wireframe;
+        self
+    }
+
+    pub fn only_points(mut self, only_points: bool) -> Self {
+        self.drawer.only_points = only_points;
+        self
+    }
+
+    pub fn stroke_width(mut self, stroke_width: f64) -> Self {
+        self.drawer.stroke_width = stroke_width;
+        self
+    }
+
+    pub fn stroke_color(mut self, stroke_color: Option<usvg::Color>) -> Self {
+        self.drawer.stroke_color = stroke_color;
+        self
+    }
+
+    pub fn stroke_opacity(mut self, stroke_opacity: f64) -> Self {
+        self.drawer.stroke_opacity = stroke_opacity;
+        self
+    }
+
+    pub fn stroke_mode(mut self, stroke_mode: StrokeMode) -> Self {
+        self.drawer.stroke_mode = stroke_mode;
+        self
+    }
+
+    pub fn backend(mut self, backend: RenderBackend) -> Self {
+        self.drawer.backend = backend;
+        self
+    }
+
+    pub fn seamless(mut self, seamless: bool) -> Self {
+        self.drawer.seamless = seamless;
+        self
+    }
+
+    pub fn vertex_radius(mut self, vertex_radius: f64) -> Self {
+        self.drawer.vertex_radius = vertex_radius;
+        self
+    }
+
+    pub fn vertex_color(mut self, vertex_color: Option<usvg::Color>) -> Self {
+        self.drawer.vertex_color = vertex_color;
+        self
+    }
+
+    pub fn vertex_opacity(mut self, vertex_opacity: f64) -> Self {
+        self.drawer.vertex_opacity = vertex_opacity;
+        self
+    }
+
+    pub fn vertex_sizing(mut self, vertex_sizing: VertexSizing) -> Self {
+        self.drawer.vertex_sizing = vertex_sizing;
+        self
+    }
+
+    pub fn with_background(mut self, with_background: bool) -> Self {
+        self.drawer.with_background = with_background;
+        self
+    }
+
+    pub fn background_color(mut self, background_color: Option<usvg::Color>) -> Self {
+        self.drawer.background_color = background_color;
+        self
+    }
+
+    pub fn background_opacity(mut self, background_opacity: f64) -> Self {
+        self.drawer.background_opacity = background_opacity;
+        self
+    }
+
+    pub fn adjust(mut self, adjust: ColorAdjust) -> Self {
+        self.drawer.adjust = adjust;
+        self
+    }
+
+    pub fn palette(mut self, palette: Option<Palette>) -> Self {
+        self.drawer.palette = palette;
+        self
+    }
+
+    pub fn build(self) -> Result<Drawer> {
+        self.drawer.validate()?;
+        Ok(self.drawer)
+    }
+}
+
+impl Drawer {
+
+    pub fn builder() -> DrawerBuilder {
+        DrawerBuilder::default()
+    }
+
+    /// Checks that every parameter is in its valid range.
+    pub fn validate(&self) -> Result<()> {
+        if !(self.stroke_width.is_finite() && self.stroke_width >= 0.0) {
+            return Err(Error::invalid_parameter(
+                "stroke_width",
+                format!("must be a positive number, got {}", self.stroke_width),
+            ));
+        }
+        if self.only_wireframe && self.stroke_width == 0.0 {
+            return Err(Error::invalid_parameter(
+                "stroke_width",
+                "must be greater than 0 when only the wireframe is drawn",
+            ));
+        }
+        if !(self.vertex_radius.is_finite() && self.vertex_radius >= 0.0) {
+            return Err(Error::invalid_parameter(
+                "vertex_radius",
+                format!("must be a positive number, got {}", self.vertex_radius),
+            ));
+        }
+        if self.only_points && self.vertex_radius == 0.0 {
+            return Err(Error::invalid_parameter(
+                "vertex_radius",
+                "must be greater than 0 when only the points are drawn",
+            ));
+        }
+        if self.only_points && self.only_wireframe {
+            return Err(Error::invalid_parameter(
+                "only_points",
+                "can not be combined with only_wireframe, draw the wireframe with the vertex markers instead",
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.vertex_opacity) {
+            return Err(Error::invalid_parameter(
+                "vertex_opacity",
+                format!("must be between 0 and 1, got {}", self.vertex_opacity),
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.stroke_opacity) {
+            return Err(Error::invalid_parameter(
+                "stroke_opacity",
+                format!("must be between 0 and 1, got {}", self.stroke_opacity),
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.background_opacity) {
+            return Err(Error::invalid_parameter(
+                "background_opacity",
+                format!("must be between 0 and 1, got {}", self.background_opacity),
+            ));
+        }
+        self.stroke_mode.validate()?;
+        self.adjust.validate()?;
+        if let Some(palette) = &self.palette {
+            palette.validate()?;
+        }
+        Ok(())
+    }
+}
+
+fn pixel_at(source_image: &RgbaImage, x: f64, y: f64) -> Rgba<u8> {
+    let (width, height) = source_image.dimensions();
+    let x = (x.max(0.0) as u32).min(width - 1);
+    let y = (y.max(0.0) as u32).min(height - 1);
+    *source_image.get_pixel(x, y)
+}
+
+/// Samples the color of the source image under the triangle.
+///
+/// The color is the one of the pixel at the center of the triangle, or of the most opaque pixel
+/// half way between the center and the vertices when the center is transparent. Returns `None`
+/// when every sampled pixel is fully transparent.
+fn sample_color(source_image: &RgbaImage, triangle: &Triangle) -> Option<Rgba<u8>> {
+    let center = triangle.center();
+    let pixel = pixel_at(source_image, center.x, center.y);
+    if pixel[3] > 0 {
+        return Some(pixel);
+    }
+
+    triangle.vertex().iter()
+        .map(|vertex| pixel_at(source_image, (center.x + vertex.x) / 2.0, (center.y + vertex.y) / 2.0))
+        .filter(|pixel| pixel[3] > 0)
+        .max_by_key(|pixel| pixel[3])
+}
+
+/// Samples the color of the source image along the edges of the triangle: the average of the
+/// non transparent pixels at the middle of each edge. Returns `None` when they are all transparent.
+fn sample_edge_color(source_image: &RgbaImage, triangle: &Triangle) -> Option<Rgba<u8>> {
+    let vertex = triangle.vertex();
+    let pixels: Vec<Rgba<u8>> = (0..3)
+        .map(|i| {
+            let (from, to) = (vertex[i], vertex[(i + 1) % 3]);
+            pixel_at(source_image, (from.x + to.x) / 2.0, (from.y + to.y) / 2.0)
+        })
+        .filter(|pixel| pixel[3] > 0)
+        .collect();
+    if pixels.is_empty() {
+        return None;
+    }
+
+    let count = pixels.len() as u32;
+    let channel = |i: usize| ((pixels.iter().map(|pixel| pixel[i] as u32).sum::<u32>() + count / 2) / count) as u8;
+    Some(Rgba::from([channel(0), channel(1), channel(2), channel(3)]))
+}
+
+impl Drawer {
+
+    /// Returns the paint of the background of the drawings, if any.
+    pub fn background(&self) -> Option<Paint> {
+        self.with_background.then(|| Paint {
+            color: self.background_color.unwrap_or(usvg::Color::white()),
+            opacity: self.background_opacity,
+        })
+    }
+
+    /// Builds the shape of each triangle. The triangles over fully transparent areas of the source
+    /// image have no fill, only their stroke, and are `None` without a stroke. The parameters are
+    /// not checked, see [`Drawer::validate`].
+    pub fn shapes(&self, source_image: &RgbaImage, triangles: &[Triangle]) -> Vec<Option<Shape>> {
+        self.shapes_with(source_image, triangles, &self.finisher(source_image))
+    }
+
+    /// Builds the marker of each vertex of the triangles, none when `vertex_radius` is 0. The
+    /// vertices over fully transparent areas of the source image are skipped unless the markers
+    /// have a `vertex_color`. The parameters are not checked, see [`Drawer::validate`].
+    ///
+    /// [`VertexSizing::Edge`] sizes the markers with `edges`, the edges the points were sampled on,
+    /// see [`Triangulation::detect_edges`](crate::Triangulation::detect_edges), and fails without them.
+    pub fn markers(&self, source_image: &RgbaImage, triangles: &[Triangle], edges: Option<&Edges>) -> Result<Vec<Marker>> {
+        self.markers_with(source_image, triangles, edges, &self.finisher(source_image))
+    }
+
+    /// Draws the triangles like [`Drawable::draw`], the markers sized by [`VertexSizing::Edge`]
+    /// follow `edges`, see [`Drawer::markers`]. [`Drawable::draw`] has no edges, it only draws the
+    /// markers of the other sizings.
+    pub fn draw_with_edges(&self, source_image: RgbaImage, triangles: Vec<Triangle>, edges: Option<&Edges>) -> Result<Drawing> {
+        self.validate()?;
+        if triangles.is_empty() {
+            return Err(Error::DegenerateGeometry("there is no triangle to draw".to_owned()));
+        }
+
+        let (width, height) = source_image.dimensions();
+        if width == 0 || height == 0 {
+            return Err(Error::DegenerateGeometry(format!("the source image is empty ({}x{})", width, height)));
+        }
+
+        // skip the fills over fully transparent areas of the source image
+        let finish = self.finisher(&source_image);
+        let shapes = self.shapes_with(&source_image, &triangles, &finish).into_iter().flatten().collect();
+        let markers = self.markers_with(&source_image, &triangles, edges, &finish)?;
+
+        Ok(Drawing::new(width as f64, height as f64, self.background(), shapes)
+            .with_markers(markers)
+            .with_backend(self.backend)
+            .with_seamless(self.seamless))
+    }
+
+    /// Whether the markers are sized with the edges of the source image, see [`Drawer::markers`].
+    pub fn sizes_with_edges(&self) -> bool {
+        self.vertex_radius > 0.0 && self.vertex_sizing == VertexSizing::Edge
+    }
+
+    /// Returns the function applying the color adjustments and the palette to the sampled colors.
+    fn finisher(&self, source_image: &RgbaImage) -> impl Fn(Rgba<u8>) -> Rgba<u8> + '_ {
+        // the palette is extracted from the adjusted colors, the ones it replaces
+        let quantizer = self.palette.as_ref().map(|palette| {
+            if self.adjust.is_identity() || matches!(palette, Palette::Colors(_)) {
+                return Quantizer::new(&palette.colors(source_image));
+            }
+            let mut adjusted = source_image.clone();
+            for pixel in adjusted.pixels_mut() {
+                *pixel = self.adjust.apply(*pixel);
+            }
+            Quantizer::new(&palette.colors(&adjusted))
+        });
+        move |pixel: Rgba<u8>| {
+            let pixel = self.adjust.apply(pixel);
+            quantizer.as_ref().map(|quantizer| quantizer.nearest(pixel)).unwrap_or(pixel)
+        }
+    }
+
+    fn shapes_with(&self, source_image: &RgbaImage, triangles: &[Triangle], finish: &impl Fn(Rgba<u8>) -> Rgba<u8>) -> Vec<Option<Shape>> {
+        triangles.iter()
+            .map(|triangle| {
+                // over fully transparent areas there is no fill, the stroke is kept when its color
+                // does not come from the fill
+                let pixel = sample_color(source_image, triangle).map(finish);
+
+                let stroke = (self.stroke_width > 0.0 && !self.only_points)
+                    .then(|| {
+                        let stroke_paint = match self.stroke_mode {
+                            StrokeMode::Fixed => Paint {
+                                color: self.stroke_color.unwrap_or(usvg::Color::black()),
+                                opacity: self.stroke_opacity,
+                            },
+                            StrokeMode::Sampled => {
+                                let edge = sample_edge_color(source_image, triangle).map(finish).or(pixel)?;
+                                sampled_paint(edge, self.stroke_opacity)
+                            }
+                            mode => sampled_paint(mode.shade_fill(pixel?), self.stroke_opacity),
+                        };
+                        Some(Stroke { paint: stroke_paint, width: self.stroke_width })
+                    })
+                    .flatten();
+                if pixel.is_none() && stroke.is_none() {
+                    return None;
+                }
+
+                Some(Shape {
+                    vertex: triangle.vertex(),
+                    fill: pixel.filter(|_| !self.only_wireframe && !self.only_points).map(|pixel| sampled_paint(pixel, 1.0)),
+                    stroke,
+                })
+            })
+            .collect()
+    }
+
+    fn markers_with(
+        &self,
+        source_image: &RgbaImage,
+        triangles: &[Triangle],
+        edges: Option<&Edges>,
+        finish: &impl Fn(Rgba<u8>) -> Rgba<u8>,
+    ) -> Result<Vec<Marker>> {
+        if self.vertex_radius <= 0.0 {
+            return Ok(vec![]);
+        }
+        if self.vertex_sizing == VertexSizing::Edge && edges.is_none() {
+            return Err(Error::invalid_parameter(
+                "vertex_sizing",
+                "the edge sizing needs the edges the points were sampled on, see Drawer::draw_with_edges",
+            ));
+        }
+
+        let mut seen = HashSet::new();
+        let markers = triangles.iter()
+            .flat_map(|triangle| triangle.vertex())
+            .filter(|point| seen.insert((point.x.to_bits(), point.y.to_bits())))
+            .filter_map(|center| {
+                let paint = match self.vertex_color {
+                    Some(color) => Paint { color, opacity: self.vertex_opacity },
+                    None => {
+                        let pixel = pixel_at(source_image, center.x, center.y);
+                        if pixel[3] == 0 {
+                            return None;
+                        }
+                        sampled_paint(finish(pixel), self.vertex_opacity)
+                    }
+                };
+                let radius = match self.vertex_sizing {
+                    VertexSizing::Fixed => self.vertex_radius,
+                    VertexSizing::Edge => {
+                        let strength = edges.map(|edges| edges.strength(center)).unwrap_or(0.0);
+                        self.vertex_radius * (0.25 + 0.75 * strength)
+                    }
+                };
+                Some(Marker { center, radius, paint })
+            })
+            .collect();
+        Ok(markers)
+    }
+}
+
+/// Paint of a color sampled on the source image, its alpha lowers the `opacity`.
+fn sampled_paint(pixel: Rgba<u8>, opacity: f64) -> Paint {
+    Paint {
+        color: usvg::Color::new_rgb(pixel[0], pixel[1], pixel[2]),
+        opacity: opacity * pixel[3] as f64 / 255.0,
+    }
+}
+
+impl Drawable for Drawer {
+    fn draw(&self, source_image: ImageBuffer<Rgba<u8>, Vec<u8>>, triangles: Vec<Triangle>) -> Result<Drawing> {
+        self.draw_with_edges(source_image, triangles, None)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use image::{Rgba, RgbaImage};
+
+    use crate::adjust::ColorAdjust;
+    use crate::color::Color;
+    use crate::delaunay::{Delaunay, Point, Triangle};
+    use crate::error::test::assert_invalid;
+    use crate::palette::Palette;
+    use crate::Triangulation;
+    use super::{sample_color, sample_edge_color, Drawable, Drawer, Fit, RenderBackend, StrokeMode, VertexSizing};
+
+    #[test]
+    fn test_fit_to() {
+        let (width, height) = (400.0, 200.0);
+
+        assert_eq!(Fit::Original.fit_to(width, height), Some((400, 200)));
+        assert_eq!(Fit::Width(100).fit_to(width, height), Some((100, 50)));
+        assert_eq!(Fit::Height(100).fit_to(width, height), Some((200, 100)));
+        assert_eq!(Fit::Size(100, 100).fit_to(width, height), Some((100, 50)));
+        assert_eq!(Fit::Size(1000, 300).fit_to(width, height), Some((600, 300)));
+        assert_eq!(Fit::Stretch(100, 100).fit_to(width, height), Some((100, 100)));
+        assert_eq!(Fit::Zoom(4.0).fit_to(width, height), Some((1600, 800)));
+        assert_eq!(Fit::Zoom(0.25).fit_to(width, height), Some((100, 50)));
+    }
+
+    #[test]
+    fn test_fit_to_invalid() {
+        assert_eq!(Fit::Zoom(0.0).fit_to(400.0, 200.0), None);
+        assert_eq!(Fit::Zoom(-1.0).fit_to(400.0, 200.0), None);
+        assert_eq!(Fit::Width(0).fit_to(400.0, 200.0), None);
+    }
+
+    #[test]
+    fn test_builder() {
+        let drawer = Drawer::builder()
+            .only_wireframe(false)
+            .stroke_width(0.0)
+            .build()
+            .unwrap();
+        assert!(!drawer.only_wireframe);
+        assert_eq!(drawer.stroke_width, 0.0);
+
+        assert!(Drawer::builder().build().is_ok());
+    }
+
+    #[test]
+    fn test_builder_invalid_parameters() {
+        let test_cases = vec![
+            Drawer::builder().stroke_width(-1.0),
+            Drawer::builder().stroke_width(f64::INFINITY),
+            Drawer::builder().stroke_width(f64::NAN),
+            Drawer::builder().only_wireframe(true).stroke_width(0.0),
+        ];
+
+        for builder in test_cases {
+            assert_invalid(builder.build(), "stroke_width");
+        }
+
+        for opacity in [-0.1, 1.5, f64::NAN] {
+            assert_invalid(Drawer::builder().background_opacity(opacity).build(), "background_opacity");
+            assert_invalid(Drawer::builder().stroke_opacity(opacity).build(), "stroke_opacity");
+        }
+    }
+
+    #[test]
+    fn test_sample_color() {
+        // left half transparent, right half half-transparent red
+        let image = RgbaImage::from_fn(20, 10, |x, _| {
+            if x < 10 { Rgba::from([0, 0, 0, 0]) } else { Rgba::from([255, 0, 0, 128]) }
+        });
+
+        let transparent = Triangle::new(Point { x: 0.0, y: 0.0 }, Point { x: 8.0, y: 0.0 }, Point { x: 0.0, y: 9.0 });
+        assert_eq!(sample_color(&image, &transparent), None);
+
+        let opaque = Triangle::new(Point { x: 12.0, y: 0.0 }, Point { x: 20.0, y: 0.0 }, Point { x: 20.0, y: 10.0 });
+        assert_eq!(sample_color(&image, &opaque), Some(Rgba::from([255, 0, 0, 128])));
+
+        // the center is transparent but the triangle overlaps the red half
+        let overlapping = Triangle::new(Point { x: 0.0, y: 0.0 }, Point { x: 20.0, y: 0.0 }, Point { x: 0.0, y: 10.0 });
+        assert_eq!(sample_color(&image, &overlapping), Some(Rgba::from([255, 0, 0, 128])));
+    }
+
+    #[test]
+    fn test_draw_transparent_source() {
+        let image = RgbaImage::from_fn(20, 10, |x, _| {
+            if x < 10 { Rgba::from([0, 0, 0, 0]) } else { Rgba::from([0, 0, 255, 128]) }
+        });
+        let triangles = vec![
+            Triangle::new(Point { x: 0.0, y: 0.0 }, Point { x: 10.0, y: 0.0 }, Point { x: 0.0, y: 10.0 }),
+            Triangle::new(Point { x: 10.0, y: 0.0 }, Point { x: 20.0, y: 0.0 }, Point { x: 20.0, y: 10.0 }),
+        ];
+
+        let drawer = Drawer { with_background: false, stroke_width: 0.0, ..Default::default() };
+        let pixmap = drawer.draw(image.clone(), triangles.clone()).unwrap().render().unwrap();
+
+        // the transparent triangle is skipped, the other one keeps the alpha of the source
+        assert_eq!(pixmap.pixel(2, 2).unwrap().alpha(), 0);
+        let pixel = pixmap.pixel(18, 2).unwrap();
+        assert!((127..=129).contains(&pixel.alpha()));
+
+        // the wireframe has no hole over the transparent area, its strokes are kept without a fill
+        let wireframe = Drawer { only_wireframe: true, stroke_width: 1.0, ..drawer.clone() };
+        let drawing = wireframe.draw(image.clone(), triangles.clone()).unwrap();
+        assert_eq!(drawing.shapes().len(), 2);
+        let filled = Drawer { stroke_width: 1.0, ..drawer.clone() };
+        let shapes = filled.shapes(&image, &triangles);
+        assert!(shapes[0].is_some_and(|shape| shape.fill.is_none() && shape.stroke.is_some()));
+        assert!(shapes[1].is_some_and(|shape| shape.fill.is_some()));
+        let shaded = Drawer { stroke_mode: StrokeMode::Fill, ..filled };
+        assert!(shaded.shapes(&image, &triangles)[0].is_none());
+    }
+
+    #[test]
+    fn test_draw_with_palette() {
+        let image = RgbaImage::from_fn(20, 10, |x, _| {
+            if x < 10 { Rgba::from([250, 10, 10, 255]) } else { Rgba::from([10, 10, 180, 128]) }
+        });
+        let triangles = vec![
+            Triangle::new(Point { x: 0.0, y: 0.0 }, Point { x: 10.0, y: 0.0 }, Point { x: 0.0, y: 10.0 }),
+            Triangle::new(Point { x: 10.0, y: 0.0 }, Point { x: 20.0, y: 0.0 }, Point { x: 20.0, y: 10.0 }),
+        ];
+        let palette = Palette::Colors(vec![Color::new_rgb(200, 0, 0), Color::new_rgb(0, 0, 255)]);
+
+        let drawer = Drawer { with_background: false, stroke_width: 0.0, palette: Some(palette), ..Default::default() };
+        let image = drawer.draw(image, triangles).unwrap().to_image_with(Fit::Original).unwrap();
+
+        // the fills are snapped to the palette, keeping the alpha of the source
+        assert_eq!(*image.get_pixel(2, 2), Rgba::from([200, 0, 0, 255]));
+        let pixel = image.get_pixel(18, 2);
+        assert_eq!((pixel[0], pixel[1], pixel[2]), (0, 0, 255));
+        assert!((127..=129).contains(&pixel[3]));
+
+        assert!(Drawer::builder().palette(Some(Palette::KMeans(0))).build().is_err());
+    }
+
+    #[test]
+    fn test_palette_after_adjust() {
+        let image = RgbaImage::from_fn(20, 10, |x, _| {
+            if x < 10 { Rgba::from([20, 200, 40, 255]) } else { Rgba::from([30, 40, 220, 255]) }
+        });
+        let triangles = vec![
+            Triangle::new(Point { x: 0.0, y: 0.0 }, Point { x: 10.0, y: 0.0 }, Point { x: 0.0, y: 10.0 }),
+            Triangle::new(Point { x: 10.0, y: 0.0 }, Point { x: 20.0, y: 0.0 }, Point { x: 20.0, y: 10.0 }),
+        ];
+        let adjust = ColorAdjust { sepia: 1.0, ..Default::default() };
+
+        // the extracted palette holds the sepia tones, not the original green and blue
+        for palette in [Palette::KMeans(2), Palette::MedianCut(2)] {
+            let drawer = Drawer { adjust: adjust.clone(), palette: Some(palette.clone()), ..Default::default() };
+            for (shape, x) in drawer.shapes(&image, &triangles).into_iter().zip([2, 18]) {
+                let expected = adjust.apply(*image.get_pixel(x, 2));
+                let fill = shape.unwrap().fill.unwrap().color;
+                assert_eq!((fill.red, fill.green, fill.blue), (expected[0], expected[1], expected[2]), "{:?}", palette);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stroke_mode_from_str() {
+        assert_eq!("fill".parse(), Ok(StrokeMode::Fill));
+        assert_eq!("Sampled".parse(), Ok(StrokeMode::Sampled));
+        assert_eq!("darker".parse(), Ok(StrokeMode::Darker(StrokeMode::DEFAULT_FACTOR)));
+        assert_eq!("lighter:0.5".parse(), Ok(StrokeMode::Lighter(0.5)));
+        for mode in [StrokeMode::Fixed, StrokeMode::Darker(0.25)] {
+            assert_eq!(mode.to_string().parse(), Ok(mode));
+        }
+
+        for invalid in ["", "dark", "fill:0.5", "darker:abc", "darker:1.5", "lighter:-1"] {
+            assert!(invalid.parse::<StrokeMode>().is_err(), "`{}` should be invalid", invalid);
+        }
+        assert!(Drawer::builder().stroke_mode(StrokeMode::Darker(2.0)).build().is_err());
+    }
+
+    #[test]
+    fn test_stroke_mode_colors() {
+        let fill = Rgba::from([100, 200, 0, 128]);
+        assert_eq!(StrokeMode::Fill.shade_fill(fill), fill);
+        assert_eq!(StrokeMode::Darker(0.5).shade_fill(fill), Rgba::from([50, 100, 0, 128]));
+        assert_eq!(StrokeMode::Lighter(0.5).shade_fill(fill), Rgba::from([178, 228, 128, 128]));
+        assert_eq!(StrokeMode::Darker(1.0).shade_fill(fill), Rgba::from([0, 0, 0, 128]));
+
+        // red top half, blue bottom half: the middle of two edges of the triangle is in the blue half
+        let image = RgbaImage::from_fn(20, 20, |_, y| {
+            if y < 10 { Rgba::from([255, 0, 0, 255]) } else { Rgba::from([0, 0, 255, 255]) }
+        });
+        let triangle = Triangle::new(Point { x: 0.0, y: 0.0 }, Point { x: 19.0, y: 0.0 }, Point { x: 10.0, y: 20.0 });
+        assert_eq!(sample_edge_color(&image, &triangle), Some(Rgba::from([85, 0, 170, 255])));
+        assert_eq!(sample_edge_color(&RgbaImage::new(20, 20), &triangle), None);
+    }
+
+    #[test]
+    fn test_draw_stroke_same_as_fill() {
+        let image = RgbaImage::from_pixel(20, 20, Rgba::from([0, 160, 0, 255]));
+        let triangles = vec![Triangle::new(Point { x: 2.0, y: 2.0 }, Point { x: 18.0, y: 2.0 }, Point { x: 2.0, y: 18.0 })];
+
+        let drawer = Drawer { only_wireframe: true, stroke_width: 2.0, stroke_mode: StrokeMode::Fill, ..Default::default() };
+        let image = drawer.draw(image, triangles).unwrap().to_image_with(Fit::Original).unwrap();
+
+        // the stroke has the color of the triangle instead of black
+        assert_eq!(*image.get_pixel(10, 2), Rgba::from([0, 160, 0, 255]));
+        assert_eq!(*image.get_pixel(6, 6), Rgba::from([255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn test_render_backends() {
+        assert_eq!("Raster".parse(), Ok(RenderBackend::Raster));
+        assert_eq!(RenderBackend::Svg.to_string().parse(), Ok(RenderBackend::Svg));
+        assert!("gpu".parse::<RenderBackend>().is_err());
+
+        // a fan of triangles over a gradient, drawn with strokes on a translucent background
+        let image = RgbaImage::from_fn(40, 30, |x, y| Rgba::from([(x * 6) as u8, (y * 8) as u8, 100, 255]));
+        let center = Point { x: 20.0, y: 15.0 };
+        let corners = [(0.0, 0.0), (40.0, 0.0), (40.0, 30.0), (0.0, 30.0)].map(|(x, y)| Point { x, y });
+        let triangles: Vec<Triangle> = (0..4).map(|i| Triangle::new(center, corners[i], corners[(i + 1) % 4])).collect();
+        let drawer = Drawer { stroke_width: 1.5, background_opacity: 0.5, ..Default::default() };
+
+        for fit in [Fit::Original, Fit::Stretch(80, 20)] {
+            let svg = drawer.draw(image.clone(), triangles.clone()).unwrap().to_image_with(fit).unwrap();
+            let raster = Drawer { backend: RenderBackend::Raster, ..drawer.clone() }
+                .draw(image.clone(), triangles.clone()).unwrap()
+                .to_image_with(fit).unwrap();
+
+            assert_eq!(svg.dimensions(), raster.dimensions());
+            // both antialias the edges differently, so only the overall difference is bounded
+            let difference: u64 = svg.pixels().zip(raster.pixels())
+                .map(|(a, b)| a.0.iter().zip(b.0.iter()).map(|(a, b)| a.abs_diff(*b) as u64).sum::<u64>())
+                .sum();
+            let mean = difference as f64 / (svg.width() * svg.height() * 4) as f64;
+            assert!(mean < 2.0, "mean difference of {}", mean);
+        }
+    }
+
+    #[test]
+    fn test_seamless() {
+        // a solid image over a mesh of irregular triangles, the background must not show through
+        let (width, height) = (64, 48);
+        let image = RgbaImage::from_pixel(width, height, Rgba::from([200, 30, 60, 255]));
+        let mut seed = 7u64;
+        let mut random = |max: u32| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 33) as f64 / (1u64 << 31) as f64 * max as f64
+        };
+        let points = (0..60).map(|_| Point { x: random(width), y: random(height) }).collect();
+        let mut delaunay = Delaunay::new(height as f64, width as f64);
+        delaunay.add_points(points);
+        let triangles = delaunay.triangles();
+
+        let drawer = Drawer { stroke_width: 0.0, background_color: Some(Color::new_rgb(0, 255, 0).to_usvg()), ..Default::default() };
+        let leaks = |drawer: &Drawer, fit: Fit| {
+            let image = drawer.draw(image.clone(), triangles.clone()).unwrap().to_image_with(fit).unwrap();
+            image.pixels().filter(|pixel| pixel.0 != [200, 30, 60, 255]).count()
+        };
+
+        for backend in [RenderBackend::Svg, RenderBackend::Raster] {
+            let drawer = Drawer { backend, ..drawer.clone() };
+            assert!(leaks(&drawer, Fit::Original) > 0, "{} backend", backend);
+            for fit in [Fit::Original, Fit::Zoom(2.5), Fit::Stretch(100, 30)] {
+                let seamless = Drawer { seamless: true, ..drawer.clone() };
+                assert_eq!(leaks(&seamless, fit), 0, "{} backend, {:?}", backend, fit);
+            }
+        }
+
+        // the outer edges stay antialiased with the raster backend only
+        let triangle = vec![Triangle::new(Point { x: 2.0, y: 3.0 }, Point { x: 60.0, y: 10.0 }, Point { x: 20.0, y: 45.0 })];
+        let partial = |backend| {
+            let drawer = Drawer { backend, seamless: true, with_background: false, stroke_width: 0.0, ..Default::default() };
+            let image = drawer.draw(image.clone(), triangle.clone()).unwrap().to_image_with(Fit::Original).unwrap();
+            image.pixels().filter(|pixel| pixel[3] > 0 && pixel[3] < 255).count()
+        };
+        assert!(partial(RenderBackend::Raster) > 0);
+        assert_eq!(partial(RenderBackend::Svg), 0);
+    }
+
+    #[test]
+    fn test_markers() {
+        // left half transparent, right half black and white
+        let image = RgbaImage::from_fn(40, 20, |x, y| match (x, y) {
+            (0..=19, _) => Rgba::from([0, 0, 0, 0]),
+            (_, 0..=9) => Rgba::from([255, 255, 255, 255]),
+            _ => Rgba::from([0, 0, 0, 255]),
+        });
+        let a = Point { x: 5.0, y: 5.0 };
+        let (b, c, d) = (Point { x: 30.0, y: 2.0 }, Point { x: 30.0, y: 10.0 }, Point { x: 36.0, y: 18.0 });
+        let triangles = vec![Triangle::new(a, b, c), Triangle::new(b, c, d)];
+
+        // a marker per vertex, the vertex over the transparent half has none
+        let drawer = Drawer { vertex_radius: 2.0, ..Default::default() };
+        let markers = drawer.markers(&image, &triangles, None).unwrap();
+        assert_eq!(markers.iter().map(|marker| marker.center).collect::<Vec<_>>(), vec![b, c, d]);
+        assert_eq!(markers[0].paint.color, Color::new_rgb(255, 255, 255).to_usvg());
+        assert_eq!(markers[2].paint.color, Color::new_rgb(0, 0, 0).to_usvg());
+        assert!(markers.iter().all(|marker| marker.radius == 2.0));
+        assert!(Drawer::default().markers(&image, &triangles, None).unwrap().is_empty());
+
+        let colored = Drawer { vertex_color: Some(Color::new_rgb(255, 0, 0).to_usvg()), vertex_opacity: 0.5, ..drawer.clone() };
+        let markers = colored.markers(&image, &triangles, None).unwrap();
+        assert_eq!(markers.len(), 4);
+        assert!(markers.iter().all(|marker| marker.paint.opacity == 0.5));
+
+        // the markers on the edge between black and white are the largest
+        let sized = Drawer { vertex_sizing: VertexSizing::Edge, ..drawer.clone() };
+        let edges = Triangulation::default().detect_edges(&image).unwrap();
+        let radii: Vec<f64> = sized.markers(&image, &triangles, Some(&edges)).unwrap().iter().map(|marker| marker.radius).collect();
+        assert_eq!(radii, vec![0.5, 2.0, 0.5]);
+        assert_invalid(sized.markers(&image, &triangles, None), "vertex_sizing");
+        assert_invalid(sized.draw(image.clone(), triangles.clone()), "vertex_sizing");
+
+        // the markers follow the edges the points were sampled on, a larger blur spreads them
+        let edges = Triangulation { blur_factor: 4, ..Default::default() }.detect_edges(&image).unwrap();
+        let radii: Vec<f64> = sized.markers(&image, &triangles, Some(&edges)).unwrap().iter().map(|marker| marker.radius).collect();
+        assert_eq!(radii[0], 0.5);
+        assert!(radii[1] > 0.5 && radii[1] < 2.0 && radii[2] > 0.5, "{:?}", radii);
+
+        // only the markers are drawn, over the background
+        let points = Drawer {
+            only_points: true,
+            vertex_color: Some(Color::new_rgb(255, 0, 0).to_usvg()),
+            background_color: Some(Color::new_rgb(255, 255, 255).to_usvg()),
+            ..drawer
+        };
+        for backend in [RenderBackend::Svg, RenderBackend::Raster] {
+            let drawer = Drawer { backend, ..points.clone() };
+            let drawing = drawer.draw(image.clone(), triangles.clone()).unwrap();
+            assert!(drawing.shapes().iter().all(|shape| shape.fill.is_none() && shape.stroke.is_none()));
+            let rendered = drawing.to_image_with(Fit::Original).unwrap();
+            assert_eq!(rendered.get_pixel(30, 10).0, [255, 0, 0, 255], "{} backend", backend);
+            assert_eq!(rendered.get_pixel(27, 10).0, [255, 255, 255, 255], "{} backend", backend);
+            assert_eq!(rendered.get_pixel(30, 6).0, [255, 255, 255, 255], "{} backend", backend);
+        }
+
+        for (builder, parameter) in [
+            (Drawer::builder().vertex_radius(-1.0), "vertex_radius"),
+            (Drawer::builder().vertex_radius(f64::NAN), "vertex_radius"),
+            (Drawer::builder().only_points(true), "vertex_radius"),
+            (Drawer::builder().only_points(true).only_wireframe(true).vertex_radius(1.0), "only_points"),
+            (Drawer::builder().vertex_opacity(1.5), "vertex_opacity"),
+        ] {
+            assert_invalid(builder.build(), parameter);
+        }
+        assert_eq!("Edge".parse::<VertexSizing>(), Ok(VertexSizing::Edge));
+    }
+}
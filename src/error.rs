@@ -0,0 +1,89 @@
+use std::fmt;
+
+
+/// Errors returned by the triangulation and drawing pipeline.
+#[derive(Debug)]
+pub enum Error {
+    /// a parameter is out of its valid range
+    InvalidParameter { name: &'static str, reason: String },
+    /// the edge detection did not find any point to triangulate
+    NoPoints,
+    /// the geometry can not be triangulated or drawn (empty image, no triangle, ...)
+    DegenerateGeometry(String),
+    /// the drawing could not be rasterized
+    Rendering(String),
+    /// the rendered image could not be encoded
+    Encoding(Box<dyn std::error::Error + Send + Sync>),
+    /// a configuration file could not be parsed or written
+    Config(Box<dyn std::error::Error + Send + Sync>),
+    /// reading or writing a file failed
+    Io(std::io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+
+    pub(crate) fn invalid_parameter(name: &'static str, reason: impl Into<String>) -> Self {
+        Error::InvalidParameter { name, reason: reason.into() }
+    }
+}
+
+impl fmt::Display for Error {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidParameter { name, reason } => write!(f, "invalid parameter `{}`: {}", name, reason),
+            Error::NoPoints => write!(f, "no points detected in the source image (try lowering the point threshold)"),
+            Error::DegenerateGeometry(reason) => write!(f, "degenerate geometry: {}", reason),
+            Error::Rendering(reason) => write!(f, "could not render the image: {}", reason),
+            Error::Encoding(_) => write!(f, "could not encode the image"),
+            Error::Config(_) => write!(f, "invalid configuration"),
+            Error::Io(_) => write!(f, "input/output error"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Encoding(source) | Error::Config(source) => Some(source.as_ref()),
+            Error::Io(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+
+    fn from(error: std::io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<image::ImageError> for Error {
+
+    fn from(error: image::ImageError) -> Self {
+        match error {
+            image::ImageError::IoError(error) => Error::Io(error),
+            error => Error::Encoding(Box::new(error)),
+        }
+    }
+}
+
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::{Error, Result};
+
+    /// Asserts that `result` is an [`Error::InvalidParameter`] on the parameter `name`.
+    #[track_caller]
+    pub(crate) fn assert_invalid<T>(result: Result<T>, name: &str) {
+        match result {
+            Err(Error::InvalidParameter { name: invalid, .. }) => assert_eq!(invalid, name),
+            Err(error) => panic!("expected an invalid `{}`, got: {}", name, error),
+            Ok(_) => panic!("expected an invalid `{}`", name),
+        }
+    }
+}
@@ -152,14 +152,12 @@ mod test {
     #[test]
     fn test_perroquet() {
         // generate a test image 
-        let image = image::open("perroquet.jpeg").unwrap();
+        let image = image::open(concat!(env!("CARGO_MANIFEST_DIR"), "/samples/perroquet.jpeg")).unwrap();
         let rgb_image = image.to_rgba8();
 
         // apply the blurred filter
         let filtered_image = sobel_filter(&rgb_image, 6);
 
-        filtered_image.save("perroquet_edge.jpeg").unwrap();
-
         // check the dimensions
         assert_eq!(image.width(), filtered_image.width());
         assert_eq!(image.height(), filtered_image.height());
@@ -1,17 +1,40 @@
-use image::{Rgba, RgbaImage, DynamicImage, ImageBuffer, Pixel};
-use rand::Rng;
+use image::{imageops, Rgba, RgbaImage, DynamicImage, ImageBuffer, Pixel};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 
+use crate::debug::Stage;
 use crate::delaunay::*;
 use crate::filter::*;
+use crate::points::PointSet;
 
 
 pub mod filter;
 pub mod delaunay;
 pub mod drawer;
+pub mod raster;
+pub mod color;
+pub mod palette;
+pub mod adjust;
+pub mod animation;
+pub mod morph;
+pub mod sequence;
+pub mod mesh;
+pub mod points;
+pub mod debug;
+pub mod stats;
+pub mod error;
+pub mod config;
+pub mod batch;
+pub mod preset;
 pub mod cli;
 
+pub use crate::error::{Error, Result};
 
 
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Triangulation {
     pub blur_factor: usize,
     pub sobel_factor: usize,
@@ -19,6 +42,86 @@ pub struct Triangulation {
     pub points_threshold: i32,
     pub point_rate: f64, 
     pub grayscale: bool,
+    /// when set, the blur, edge detection and point sampling run on a copy of the image
+    /// downscaled so that its largest side is at most `work_size` pixels; the blur and sobel
+    /// factors are scaled down with it and the point rate still counts full resolution pixels
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub work_size: Option<u32>,
+    /// when set, the points are picked with a random generator seeded with this value, so the
+    /// same image always gives the same triangulation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+/// Edges detected on a source image, see [`Triangulation::detect_edges`].
+#[derive(Debug, Clone)]
+pub struct Edges {
+    /// edge image, at the working size when `work_size` is set
+    image: RgbaImage,
+    /// scale mapping the pixels of the edge image to the pixels of the source image
+    scale: (f64, f64),
+    /// dimensions of the source image
+    source_size: (u32, u32),
+}
+
+impl Edges {
+
+    /// The edge image, at the working size when `work_size` is set.
+    pub fn image(&self) -> &RgbaImage {
+        &self.image
+    }
+
+    /// Strength of the edges under `point` of the source image, between 0 (flat) and 1.
+    pub(crate) fn strength(&self, point: Point) -> f64 {
+        match self.edge_pixel(point) {
+            Some((x, y)) => self.image.get_pixel(x, y)[0] as f64 / 255.0,
+            None => 0.0,
+        }
+    }
+
+    /// Pixel of the edge image under `point` of the source image, `None` when the image is empty.
+    pub(crate) fn edge_pixel(&self, point: Point) -> Option<(u32, u32)> {
+        let (width, height) = self.image.dimensions();
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let x = ((point.x / self.scale.0).max(0.0) as u32).min(width - 1);
+        let y = ((point.y / self.scale.1).max(0.0) as u32).min(height - 1);
+        Some((x, y))
+    }
+
+    /// Size of the block of source pixels covered by each pixel of the edge image.
+    fn cell(&self) -> (u32, u32) {
+        (self.scale.0.round().max(1.0) as u32, self.scale.1.round().max(1.0) as u32)
+    }
+
+    /// Number of source pixels covered by `edge_points` pixels of the edge image.
+    pub(crate) fn covered(&self, edge_points: usize) -> usize {
+        let (cell_width, cell_height) = self.cell();
+        edge_points * (cell_width * cell_height) as usize
+    }
+
+    /// Picks `count` random pixels of the source image among the ones covered by `edge_points`,
+    /// the pixels of the edge image, so the points are as dense as on the full resolution image.
+    /// A pixel can be picked several times.
+    pub(crate) fn pick_points(&self, edge_points: &[Point], count: usize, rng: &mut impl Rng) -> Vec<Point> {
+        if edge_points.is_empty() {
+            return vec![];
+        }
+        let (cell_width, cell_height) = self.cell();
+        let cells = (cell_width * cell_height) as usize;
+        let (width, height) = self.source_size;
+        (0..count)
+            .map(|_| {
+                let index = rng.gen_range(0..edge_points.len() * cells);
+                let (point, offset) = (edge_points[index / cells], (index % cells) as u32);
+                Point {
+                    x: ((point.x * self.scale.0).floor() + (offset % cell_width) as f64).min(width as f64 - 1.0),
+                    y: ((point.y * self.scale.1).floor() + (offset / cell_width) as f64).min(height as f64 - 1.0),
+                }
+            })
+            .collect()
+    }
 }
 
 impl Default for Triangulation {
@@ -30,36 +133,243 @@ impl Default for Triangulation {
             points_threshold: 10, 
             grayscale: false, 
             max_points: 2500,
-            point_rate: 0.075
+            point_rate: 0.075,
+            work_size: None,
+            seed: None,
         }
     }
 }
 
 
+/// Builder for [`Triangulation`], the parameters are checked by [`TriangulationBuilder::build`].
+#[derive(Default)]
+pub struct TriangulationBuilder {
+    triangulation: Triangulation,
+}
+
+impl From<Triangulation> for TriangulationBuilder {
+
+    fn from(triangulation: Triangulation) -> Self {
+        Self { triangulation }
+    }
+}
+
+impl TriangulationBuilder {
+
+    pub fn blur_factor(mut self, blur_factor: usize) -> Self {
+        self.triangulation.blur_factor = blur_factor;
+        self
+    }
+
+    pub fn sobel_factor(mut self, sobel_factor: usize) -> Self {
+        self.triangulation.sobel_factor = sobel_factor;
+        self
+    }
+
+    pub fn max_points(mut self, max_points: usize) -> Self {
+        self.triangulation.max_points = max_points;
+        self
+    }
+
+    pub fn points_threshold(mut self, points_threshold: i32) -> Self {
+        self.triangulation.points_threshold = points_threshold;
+        self
+    }
+
+    pub fn point_rate(mut self, point_rate: f64) -> Self {
+        self.triangulation.point_rate = point_rate;
+        self
+    }
+
+    pub fn grayscale(mut self, grayscale: bool) -> Self {
+        self.triangulation.grayscale = grayscale;
+        self
+    }
+
+    pub fn work_size(mut self, work_size: Option<u32>) -> Self {
+        self.triangulation.work_size = work_size;
+        self
+    }
+
+    pub fn seed(mut self, seed: Option<u64>) -> Self {
+        self.triangulation.seed = seed;
+        self
+    }
+
+    pub fn build(self) -> Result<Triangulation> {
+        self.triangulation.validate()?;
+        Ok(self.triangulation)
+    }
+}
+
+
 impl Triangulation {
 
+    pub fn builder() -> TriangulationBuilder {
+        TriangulationBuilder::default()
+    }
+
+    /// Checks that every parameter is in its valid range.
+    pub fn validate(&self) -> Result<()> {
+        if self.sobel_factor == 0 {
+            return Err(Error::invalid_parameter("sobel_factor", "must be at least 1"));
+        }
+        if self.max_points == 0 {
+            return Err(Error::invalid_parameter("max_points", "must be at least 1"));
+        }
+        if !(0..255).contains(&self.points_threshold) {
+            return Err(Error::invalid_parameter(
+                "points_threshold",
+                format!("must be between 0 and 254, got {}", self.points_threshold),
+            ));
+        }
+        if !(self.point_rate > 0.0 && self.point_rate <= 1.0) {
+            return Err(Error::invalid_parameter(
+                "point_rate",
+                format!("must be greater than 0 and at most 1, got {}", self.point_rate),
+            ));
+        }
+        if self.work_size == Some(0) {
+            return Err(Error::invalid_parameter("work_size", "must be at least 1 pixel"));
+        }
+        Ok(())
+    }
+
+    pub fn generate_triangle(&self,  image: DynamicImage) -> Result<(Vec<Triangle>, RgbaImage)> {
+        let source_image = self.prepare(image)?;
+        let points = self.sample_points(&source_image)?;
+        let triangles = self.triangulate(&source_image, points)?;
+        Ok((triangles, source_image))
+    }
+
+    /// Converts the image into the source image used to sample the points and the colors.
+    pub fn prepare(&self, image: DynamicImage) -> Result<RgbaImage> {
+        self.validate()?;
+        let (width, height) = (image.width(), image.height());
+        if width == 0 || height == 0 {
+            return Err(Error::DegenerateGeometry(format!("the source image is empty ({}x{})", width, height)));
+        }
 
-    pub fn generate_triangle(&self,  image: DynamicImage) -> (Vec<Triangle>, RgbaImage) {
-        let mut source_image = image.to_rgba8();
-        let (width, height) = source_image.dimensions();
         if self.grayscale {
-            source_image = image.grayscale().to_rgba8();
-        } 
+            Ok(image.grayscale().to_rgba8())
+        } else {
+            Ok(image.to_rgba8())
+        }
+    }
+
+    /// Samples the points on the edges of the source image.
+    pub fn sample_points(&self, source_image: &RgbaImage) -> Result<Vec<Point>> {
+        self.validate()?;
+        self.sample(source_image.dimensions(), None, || self.points_on(&self.edges(source_image)))
+    }
 
-        let blur_image = blur_filter(&source_image, self.blur_factor);
-        let edge_image = sobel_filter(&blur_image, self.sobel_factor);
-        let points = self.get_points(&edge_image);
+    /// Combines the points of `point_set` with the points sampled on the edges of the source image,
+    /// the edge detection is skipped when they replace the sampled points.
+    pub fn sample_points_with(&self, source_image: &RgbaImage, point_set: &PointSet) -> Result<Vec<Point>> {
+        self.validate()?;
+        self.sample(source_image.dimensions(), Some(point_set), || self.points_on(&self.edges(source_image)))
+    }
+
+    /// Samples the points on edges already detected, combined with the points of `point_set`
+    /// when it is given, see [`Triangulation::sample_points_with`].
+    pub fn sample_edges(&self, edges: &Edges, point_set: Option<&PointSet>) -> Result<Vec<Point>> {
+        self.validate()?;
+        self.sample(edges.source_size, point_set, || self.points_on(edges))
+    }
+
+    fn sample(&self, (width, height): (u32, u32), point_set: Option<&PointSet>, detect: impl FnOnce() -> Vec<Point>) -> Result<Vec<Point>> {
+        let points = match point_set {
+            Some(point_set) => {
+                point_set.validate(width, height)?;
+                point_set.combine(detect)
+            }
+            None => detect(),
+        };
+        if points.is_empty() {
+            return Err(Error::NoPoints);
+        }
+        Ok(points)
+    }
+
+    /// Triangulates the points over the whole source image.
+    pub fn triangulate(&self, source_image: &RgbaImage, points: Vec<Point>) -> Result<Vec<Triangle>> {
+        let (width, height) = source_image.dimensions();
         let mut delonay = Delaunay::new(height as f64, width as f64);
         delonay.add_points(points);
 
         let triangles = delonay.triangles();
-        (triangles, source_image)
+        if triangles.is_empty() {
+            return Err(Error::DegenerateGeometry("the triangulation did not produce any triangle".to_owned()));
+        }
+        Ok(triangles)
     }
 
-    fn get_points(&self, image: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> Vec<Point> {
-        let mut rng = rand::thread_rng();
+    /// Blurs the source image and detects its edges, to sample the points with
+    /// [`Triangulation::sample_edges`].
+    pub fn detect_edges(&self, source_image: &RgbaImage) -> Result<Edges> {
+        self.validate()?;
+        Ok(self.edges(source_image))
+    }
+
+    /// Detects the edges like [`Triangulation::detect_edges`] and calls `inspect` with the
+    /// intermediate images, to tune the blur factor, the sobel factor and the points threshold:
+    /// the blurred image, the edges and the mask of the pixels where the points are sampled (at the
+    /// working size when `work_size` is set).
+    pub fn inspect_edges(&self, source_image: &RgbaImage, mut inspect: impl FnMut(Stage, &RgbaImage)) -> Result<Edges> {
+        self.validate()?;
+        let (blur_image, scale) = self.blur_image(source_image);
+        inspect(Stage::Blur, &blur_image);
+        let edge_image = sobel_filter(&blur_image, working_radius(self.sobel_factor, scale).max(1));
+        inspect(Stage::Edge, &edge_image);
+        let (width, height) = edge_image.dimensions();
+        inspect(Stage::Mask, &debug::mask_image(width, height, &self.edge_points(&edge_image)));
+        Ok(Edges { image: edge_image, scale, source_size: source_image.dimensions() })
+    }
+
+    /// Blurs the image and detects its edges, on a downscaled copy when `work_size` is set.
+    fn edges(&self, source_image: &RgbaImage) -> Edges {
+        let (blur_image, scale) = self.blur_image(source_image);
+        let edge_image = sobel_filter(&blur_image, working_radius(self.sobel_factor, scale).max(1));
+        Edges { image: edge_image, scale, source_size: source_image.dimensions() }
+    }
+
+    /// Picks the points on the edges, in the pixels of the source image.
+    fn points_on(&self, edges: &Edges) -> Vec<Point> {
+        let points = self.edge_points(&edges.image);
+        let limit = self.point_limit(edges.covered(points.len()));
+        match self.seed {
+            Some(seed) => edges.pick_points(&points, limit, &mut StdRng::seed_from_u64(seed)),
+            None => edges.pick_points(&points, limit, &mut rand::thread_rng()),
+        }
+    }
+
+    /// Blurs the image, or its downscaled copy when `work_size` is set (with the blur radius scaled
+    /// down by the same ratio).
+    fn blur_image(&self, image: &RgbaImage) -> (RgbaImage, (f64, f64)) {
+        let (width, height) = image.dimensions();
+        let working_image = match self.work_size {
+            Some(size) if width.max(height) > size && size > 0 => {
+                let ratio = size as f64 / width.max(height) as f64;
+                let working_width = ((width as f64 * ratio).round() as u32).max(1);
+                let working_height = ((height as f64 * ratio).round() as u32).max(1);
+                Some(imageops::resize(image, working_width, working_height, imageops::FilterType::Triangle))
+            }
+            _ => None,
+        };
+
+        let Some(working_image) = working_image else {
+            return (blur_filter(image, self.blur_factor), (1.0, 1.0));
+        };
+
+        let scale_x = width as f64 / working_image.width() as f64;
+        let scale_y = height as f64 / working_image.height() as f64;
+        let blur_factor = working_radius(self.blur_factor, (scale_x, scale_y));
+        (blur_filter(&working_image, blur_factor), (scale_x, scale_y))
+    }
+
+    /// Returns the pixels of the edge image whose neighbourhood is above the points threshold.
+    pub(crate) fn edge_points(&self, image: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> Vec<Point> {
         let mut points: Vec<Point> = vec![];
-        let mut dpoints: Vec<Point> = vec![];
         let (width, height) = image.dimensions();
 
         for  x in 0..width {
@@ -83,21 +393,30 @@ impl Triangulation {
                 }
             }
         }
+        points
+    }
 
-        let limit = (points.len() as f64 * self.point_rate).min(self.max_points as f64) as usize;
-        for _ in 0..limit {
-            let j = rng.gen_range(0..points.len());
-            dpoints.push(points[j]);
-        }
-        return  dpoints;
+    /// Number of points picked among `edge_points` edge points (in pixels of the source image).
+    pub(crate) fn point_limit(&self, edge_points: usize) -> usize {
+        (edge_points as f64 * self.point_rate).min(self.max_points as f64) as usize
     }
 }
 
+/// Radius of a filter on the working copy scaled by `scale`, giving the same look as `radius` on
+/// the source image.
+fn working_radius(radius: usize, (scale_x, scale_y): (f64, f64)) -> usize {
+    (radius as f64 / scale_x.max(scale_y)).round() as usize
+}
+
 #[cfg(test)]
 mod test {
-    use image::{Rgba, RgbaImage};
+    use image::{DynamicImage, Rgba, RgbaImage};
     use rand::Rng;
-    use super::Triangulation;
+    use super::{Edges, Error, Triangulation};
+    use crate::debug::Stage;
+    use crate::error::test::assert_invalid;
+    use crate::delaunay::Point;
+    use crate::points::{PointSet, PointsMode};
 
     #[test]
     fn test_get_points() {
@@ -116,10 +435,11 @@ mod test {
             for &max_points in &[5, 10, 20] {
                 let triangulation = Triangulation {
                     points_threshold: threshold,
-                    max_points: max_points as usize,
+                    max_points,
                     ..Default::default()
                 };
-                let points = triangulation.get_points(&img);
+                let edges = Edges { image: img.clone(), scale: (1.0, 1.0), source_size: img.dimensions() };
+                let points = triangulation.points_on(&edges);
                 
                 // Check that the number of returned points is within the expected range
                 assert!(points.len() <= max_points);
@@ -132,4 +452,139 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_detect_points_with_work_size() {
+        // create an image with a vertical edge in the middle
+        let img = RgbaImage::from_fn(400, 200, |x, _| {
+            if x < 200 { Rgba::from([0, 0, 0, 255]) } else { Rgba::from([255, 255, 255, 255]) }
+        });
+
+        let triangulation = Triangulation {
+            blur_factor: 4,
+            sobel_factor: 8,
+            point_rate: 0.05,
+            seed: Some(1),
+            ..Default::default()
+        };
+        let full = triangulation.sample_points(&img).unwrap();
+        let points = Triangulation { work_size: Some(100), ..triangulation }.sample_points(&img).unwrap();
+
+        // points are sampled on a 100x50 copy and spread over the full resolution pixels
+        points.iter().for_each(|point| {
+            assert!(point.x < (img.width() as f64));
+            assert!(point.y < (img.height() as f64));
+            assert!((point.x - 200.0).abs() < 40.0);
+        });
+        assert!(points.iter().any(|point| point.x % 4.0 != 0.0 || point.y % 4.0 != 0.0));
+
+        // the radii and the point rate keep their full resolution meaning
+        assert!(points.len() * 2 > full.len() && points.len() < full.len() * 2, "{} vs {}", points.len(), full.len());
+    }
+
+    #[test]
+    fn test_seed() {
+        let img = RgbaImage::from_fn(60, 40, |x, y| {
+            if (x / 10 + y / 10) % 2 == 0 { Rgba::from([0, 0, 0, 255]) } else { Rgba::from([255, 255, 255, 255]) }
+        });
+
+        let seeded = |seed| Triangulation { seed: Some(seed), ..Default::default() }.sample_points(&img).unwrap();
+        assert_eq!(seeded(1), seeded(1));
+        assert_ne!(seeded(1), seeded(2));
+    }
+
+    #[test]
+    fn test_sample_points_with() {
+        let blank = RgbaImage::from_pixel(20, 20, Rgba::from([128, 128, 128, 255]));
+        let point_set = PointSet { points: vec![Point { x: 5.0, y: 5.0 }, Point { x: 15.0, y: 10.0 }], mode: PointsMode::Merge };
+        let triangulation = Triangulation::default();
+
+        // the hand placed points are triangulated even without edges
+        assert_eq!(triangulation.sample_points_with(&blank, &point_set).unwrap(), point_set.points);
+        let triangles = triangulation.triangulate(&blank, point_set.points.clone()).unwrap();
+        assert!(triangles.iter().any(|triangle| triangle.vertex().contains(&point_set.points[0])));
+
+        let empty = PointSet { points: vec![], mode: PointsMode::Replace };
+        assert!(matches!(triangulation.sample_points_with(&blank, &empty), Err(Error::NoPoints)));
+        let outside = PointSet { points: vec![Point { x: 25.0, y: 5.0 }], mode: PointsMode::Replace };
+        assert!(matches!(triangulation.sample_points_with(&blank, &outside), Err(Error::InvalidParameter { .. })));
+    }
+
+    #[test]
+    fn test_inspect_edges() {
+        let img = RgbaImage::from_fn(400, 200, |x, _| {
+            if x < 200 { Rgba::from([0, 0, 0, 255]) } else { Rgba::from([255, 255, 255, 255]) }
+        });
+        let triangulation = Triangulation { work_size: Some(100), ..Default::default() };
+
+        let mut stages = vec![];
+        let edges = triangulation.inspect_edges(&img, |stage, image| stages.push((stage, image.clone()))).unwrap();
+        assert_eq!(stages.iter().map(|(stage, _)| *stage).collect::<Vec<_>>(), vec![Stage::Blur, Stage::Edge, Stage::Mask]);
+        assert!(stages.iter().all(|(_, image)| image.dimensions() == (100, 50)));
+
+        // the mask is white around the vertical edge only
+        let mask = &stages[2].1;
+        assert_eq!(mask.get_pixel(50, 25).0, [255, 255, 255, 255]);
+        assert_eq!(mask.get_pixel(5, 25).0, [0, 0, 0, 255]);
+
+        // the points are sampled on the inspected edges without detecting them again
+        assert_eq!(edges.image(), &stages[1].1);
+        let seeded = Triangulation { seed: Some(3), ..triangulation };
+        assert_eq!(seeded.sample_edges(&edges, None).unwrap(), seeded.sample_points(&img).unwrap());
+
+        // the strength is read on the edge image, at the working size
+        assert_eq!(edges.strength(Point { x: 4.0, y: 100.0 }), 0.0);
+        assert!(edges.strength(Point { x: 196.0, y: 100.0 }) > 0.0);
+        let empty = Edges { image: RgbaImage::new(0, 0), scale: (1.0, 1.0), source_size: (0, 0) };
+        assert_eq!(empty.strength(Point { x: 1.0, y: 1.0 }), 0.0);
+    }
+
+    #[test]
+    fn test_builder() {
+        let triangulation = Triangulation::builder()
+            .blur_factor(2)
+            .max_points(100)
+            .point_rate(0.5)
+            .build()
+            .unwrap();
+        assert_eq!(triangulation.blur_factor, 2);
+        assert_eq!(triangulation.max_points, 100);
+        assert_eq!(triangulation.point_rate, 0.5);
+        assert_eq!(triangulation.sobel_factor, Triangulation::default().sobel_factor);
+
+        assert!(Triangulation::builder().build().is_ok());
+    }
+
+    #[test]
+    fn test_builder_invalid_parameters() {
+        let test_cases = vec![
+            ("sobel_factor", Triangulation::builder().sobel_factor(0)),
+            ("max_points", Triangulation::builder().max_points(0)),
+            ("points_threshold", Triangulation::builder().points_threshold(-1)),
+            ("points_threshold", Triangulation::builder().points_threshold(255)),
+            ("point_rate", Triangulation::builder().point_rate(0.0)),
+            ("point_rate", Triangulation::builder().point_rate(-0.5)),
+            ("point_rate", Triangulation::builder().point_rate(1.5)),
+            ("point_rate", Triangulation::builder().point_rate(f64::NAN)),
+            ("work_size", Triangulation::builder().work_size(Some(0))),
+        ];
+
+        for (expected, builder) in test_cases {
+            assert_invalid(builder.build(), expected);
+        }
+    }
+
+    #[test]
+    fn test_generate_triangle_errors() {
+        let triangulation = Triangulation::default();
+
+        // an uniform image has no edge
+        let blank = RgbaImage::from_pixel(20, 20, Rgba::from([128, 128, 128, 255]));
+        let result = triangulation.generate_triangle(DynamicImage::ImageRgba8(blank));
+        assert!(matches!(result, Err(Error::NoPoints)));
+
+        let empty = RgbaImage::new(0, 0);
+        let result = triangulation.generate_triangle(DynamicImage::ImageRgba8(empty));
+        assert!(matches!(result, Err(Error::DegenerateGeometry(_))));
+    }
 }
@@ -3,9 +3,8 @@ use triangulation::cli;
 
 
 fn main() {
-    let result = cli::execute();
-    match result {
-        Err(e) => eprintln!("{:?}", e),
-        _ => {}
+    if let Err(e) = cli::execute() {
+        e.print();
+        std::process::exit(e.kind.exit_code());
     }
 }
@@ -0,0 +1,499 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::delaunay::{Point, Triangle};
+use crate::drawer::{Drawing, Paint};
+use crate::error::{Error, Result};
+
+
+/// File format of an exported [`Mesh`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshFormat {
+    /// vertices, triangle indices and triangle colors as flat arrays, ready for a vertex buffer
+    Json,
+    /// Wavefront OBJ, the colors are materials of a MTL file written next to it
+    Obj,
+    /// Wavefront OBJ with vertex colors, each triangle has its own vertices
+    ObjColors,
+    /// ASCII PLY, the colors are properties of the faces
+    Ply,
+    /// a feature collection with a polygon per triangle, the colors are properties of the features
+    GeoJson,
+}
+
+impl MeshFormat {
+
+    /// Guess the format from the extension of `path` (`.json`, `.obj`, `.ply` or `.geojson`).
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "json" => Some(MeshFormat::Json),
+            "obj" => Some(MeshFormat::Obj),
+            "ply" => Some(MeshFormat::Ply),
+            "geojson" => Some(MeshFormat::GeoJson),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for MeshFormat {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshFormat::Json => f.pad("json"),
+            MeshFormat::Obj => f.pad("obj"),
+            MeshFormat::ObjColors => f.pad("obj-colors"),
+            MeshFormat::Ply => f.pad("ply"),
+            MeshFormat::GeoJson => f.pad("geojson"),
+        }
+    }
+}
+
+impl FromStr for MeshFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "json" => Ok(MeshFormat::Json),
+            "obj" => Ok(MeshFormat::Obj),
+            "obj-colors" => Ok(MeshFormat::ObjColors),
+            "ply" => Ok(MeshFormat::Ply),
+            "geojson" => Ok(MeshFormat::GeoJson),
+            _ => Err(format!("unknown mesh format `{}` (expected json, obj, obj-colors, ply or geojson)", value)),
+        }
+    }
+}
+
+/// A triangle of a [`Mesh`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Face {
+    /// indices of the vertices in [`Mesh::vertices`]
+    pub indices: [usize; 3],
+    /// fill color of the triangle (the stroke color for a wireframe)
+    pub color: Paint,
+}
+
+/// The triangles of a [`Drawing`] sharing their vertices, in the pixels of the source image
+/// (the origin is the top left corner, y goes down).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mesh {
+    pub width: f64,
+    pub height: f64,
+    pub vertices: Vec<Point>,
+    pub faces: Vec<Face>,
+}
+
+/// Layout of [`MeshFormat::Json`].
+#[derive(Serialize)]
+struct JsonMesh {
+    width: f64,
+    height: f64,
+    vertices: Vec<[f64; 2]>,
+    triangles: Vec<[usize; 3]>,
+    colors: Vec<[u8; 4]>,
+}
+
+/// Layout of [`MeshFormat::Json`] read by [`parse_triangles`], the colors are ignored.
+#[derive(Deserialize)]
+struct JsonMeshInput {
+    width: Option<f64>,
+    height: Option<f64>,
+    vertices: Vec<[f64; 2]>,
+    triangles: Vec<[usize; 3]>,
+}
+
+fn rgba(paint: Paint) -> [u8; 4] {
+    [paint.color.red, paint.color.green, paint.color.blue, (paint.opacity.clamp(0.0, 1.0) * 255.0).round() as u8]
+}
+
+fn hex(paint: Paint) -> String {
+    format!("#{:02x}{:02x}{:02x}", paint.color.red, paint.color.green, paint.color.blue)
+}
+
+fn material_name(paint: Paint) -> String {
+    let [red, green, blue, alpha] = rgba(paint);
+    format!("color_{:02x}{:02x}{:02x}{:02x}", red, green, blue, alpha)
+}
+
+fn json_error(error: serde_json::Error) -> Error {
+    if error.is_io() {
+        Error::Io(error.into())
+    } else {
+        Error::Encoding(Box::new(error))
+    }
+}
+
+impl Mesh {
+
+    /// Builds the mesh of the shapes of `drawing`, the shapes without fill nor stroke are skipped.
+    pub fn from_drawing(drawing: &Drawing) -> Self {
+        let (width, height) = drawing.size();
+        let mut vertices = vec![];
+        let mut indices = HashMap::new();
+        let faces = drawing.shapes()
+            .iter()
+            .filter_map(|shape| {
+                let color = shape.fill.or(shape.stroke.map(|stroke| stroke.paint))?;
+                let indices = shape.vertex.map(|point| {
+                    *indices.entry((point.x.to_bits(), point.y.to_bits())).or_insert_with(|| {
+                        vertices.push(point);
+                        vertices.len() - 1
+                    })
+                });
+                Some(Face { indices, color })
+            })
+            .collect();
+        Self { width, height, vertices, faces }
+    }
+
+    /// Writes the mesh to `path`, a [`MeshFormat::Obj`] mesh also writes its materials next to it
+    /// (same name with the `.mtl` extension).
+    pub fn save(&self, path: impl AsRef<Path>, format: MeshFormat) -> Result<()> {
+        let path = path.as_ref();
+        let mut writer = BufWriter::new(fs::File::create(path)?);
+        if format == MeshFormat::Obj {
+            let mtl_path = path.with_extension("mtl");
+            let mut mtl_writer = BufWriter::new(fs::File::create(&mtl_path)?);
+            self.write_mtl(&mut mtl_writer)?;
+            mtl_writer.flush()?;
+            let mtl_name = mtl_path.file_name().map(|name| name.to_string_lossy().into_owned());
+            self.write_obj(&mut writer, mtl_name.as_deref())?;
+        } else {
+            self.write(&mut writer, format)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes the mesh to `writer`, a [`MeshFormat::Obj`] mesh refers to materials written by
+    /// [`Mesh::write_mtl`] without loading their file.
+    pub fn write<W: Write>(&self, mut writer: W, format: MeshFormat) -> Result<()> {
+        match format {
+            MeshFormat::Json => self.write_json(&mut writer),
+            MeshFormat::Obj => self.write_obj(&mut writer, None),
+            MeshFormat::ObjColors => self.write_obj_colors(&mut writer),
+            MeshFormat::Ply => self.write_ply(&mut writer),
+            MeshFormat::GeoJson => self.write_geojson(&mut writer),
+        }
+    }
+
+    /// Writes a material per color of the mesh, in the MTL format.
+    pub fn write_mtl<W: Write>(&self, mut writer: W) -> Result<()> {
+        let mut names = HashSet::new();
+        for face in &self.faces {
+            let name = material_name(face.color);
+            if !names.insert(name.clone()) {
+                continue;
+            }
+            let [red, green, blue, alpha] = rgba(face.color).map(|channel| channel as f64 / 255.0);
+            writeln!(writer, "newmtl {}", name)?;
+            writeln!(writer, "Kd {:.4} {:.4} {:.4}", red, green, blue)?;
+            writeln!(writer, "d {:.4}", alpha)?;
+        }
+        Ok(())
+    }
+
+    fn write_json<W: Write>(&self, writer: W) -> Result<()> {
+        let mesh = JsonMesh {
+            width: self.width,
+            height: self.height,
+            vertices: self.vertices.iter().map(|point| [point.x, point.y]).collect(),
+            triangles: self.faces.iter().map(|face| face.indices).collect(),
+            colors: self.faces.iter().map(|face| rgba(face.color)).collect(),
+        };
+        serde_json::to_writer(writer, &mesh).map_err(json_error)
+    }
+
+    fn write_obj<W: Write>(&self, mut writer: W, mtl_name: Option<&str>) -> Result<()> {
+        if let Some(mtl_name) = mtl_name {
+            writeln!(writer, "mtllib {}", mtl_name)?;
+        }
+        for point in &self.vertices {
+            writeln!(writer, "v {} {} 0", point.x, point.y)?;
+        }
+
+        let mut material = None;
+        for face in &self.faces {
+            let name = material_name(face.color);
+            if material.as_ref() != Some(&name) {
+                writeln!(writer, "usemtl {}", name)?;
+                material = Some(name);
+            }
+            let [a, b, c] = face.indices.map(|index| index + 1);
+            writeln!(writer, "f {} {} {}", a, b, c)?;
+        }
+        Ok(())
+    }
+
+    fn write_obj_colors<W: Write>(&self, mut writer: W) -> Result<()> {
+        for face in &self.faces {
+            let [red, green, blue, _] = rgba(face.color).map(|channel| channel as f64 / 255.0);
+            for index in face.indices {
+                let point = self.vertices[index];
+                writeln!(writer, "v {} {} 0 {:.4} {:.4} {:.4}", point.x, point.y, red, green, blue)?;
+            }
+        }
+        for face in 0..self.faces.len() {
+            writeln!(writer, "f {} {} {}", 3 * face + 1, 3 * face + 2, 3 * face + 3)?;
+        }
+        Ok(())
+    }
+
+    fn write_ply<W: Write>(&self, mut writer: W) -> Result<()> {
+        writeln!(writer, "ply")?;
+        writeln!(writer, "format ascii 1.0")?;
+        writeln!(writer, "element vertex {}", self.vertices.len())?;
+        for property in ["x", "y", "z"] {
+            writeln!(writer, "property float {}", property)?;
+        }
+        writeln!(writer, "element face {}", self.faces.len())?;
+        writeln!(writer, "property list uchar int vertex_indices")?;
+        for property in ["red", "green", "blue", "alpha"] {
+            writeln!(writer, "property uchar {}", property)?;
+        }
+        writeln!(writer, "end_header")?;
+
+        for point in &self.vertices {
+            writeln!(writer, "{} {} 0", point.x, point.y)?;
+        }
+        for face in &self.faces {
+            let [a, b, c] = face.indices;
+            let [red, green, blue, alpha] = rgba(face.color);
+            writeln!(writer, "3 {} {} {} {} {} {} {}", a, b, c, red, green, blue, alpha)?;
+        }
+        Ok(())
+    }
+
+    /// The coordinates are in pixels, with the y axis going down. The rings are counterclockwise in
+    /// these coordinates, as RFC 7946 requires for the exterior rings.
+    fn write_geojson<W: Write>(&self, writer: W) -> Result<()> {
+        let features: Vec<_> = self.faces.iter()
+            .map(|face| {
+                let [a, b, c] = face.indices.map(|index| [self.vertices[index].x, self.vertices[index].y]);
+                // a positive signed area is counterclockwise
+                let signed_area = (b[0] - a[0]) * (c[1] - a[1]) - (c[0] - a[0]) * (b[1] - a[1]);
+                let (b, c) = if signed_area < 0.0 { (c, b) } else { (b, c) };
+                json!({
+                    "type": "Feature",
+                    "geometry": { "type": "Polygon", "coordinates": [[a, b, c, a]] },
+                    "properties": { "fill": hex(face.color), "fill-opacity": face.color.opacity },
+                })
+            })
+            .collect();
+        let collection = json!({ "type": "FeatureCollection", "features": features });
+        serde_json::to_writer(writer, &collection).map_err(json_error)
+    }
+}
+
+/// Reads the triangles of a mesh file, its format is guessed from its extension, see
+/// [`parse_triangles`].
+pub fn load_triangles(path: impl AsRef<Path>, width: u32, height: u32) -> Result<Vec<Triangle>> {
+    let path = path.as_ref();
+    let format = MeshFormat::from_path(path).ok_or_else(|| Error::invalid_parameter(
+        "mesh",
+        format!("unknown format for `{}` (expected a .json or .obj file)", path.display()),
+    ))?;
+    parse_triangles(&fs::read_to_string(path)?, format, width, height)
+}
+
+/// Parses the triangles of a json or obj mesh, such as the ones written by [`Mesh::save`]. The
+/// vertices of a json mesh are scaled from its `width` and `height` to the `width` x `height` image,
+/// the vertices of an obj mesh are in pixels and its polygons are split into triangles.
+pub fn parse_triangles(content: &str, format: MeshFormat, width: u32, height: u32) -> Result<Vec<Triangle>> {
+    let (vertices, faces) = match format {
+        MeshFormat::Json => {
+            let mesh: JsonMeshInput = serde_json::from_str(content)
+                .map_err(|e| Error::invalid_parameter("mesh", format!("invalid json: {}", e)))?;
+            let scale_x = mesh.width.map(|mesh_width| width as f64 / mesh_width).unwrap_or(1.0);
+            let scale_y = mesh.height.map(|mesh_height| height as f64 / mesh_height).unwrap_or(1.0);
+            let vertices = mesh.vertices.iter().map(|[x, y]| Point { x: x * scale_x, y: y * scale_y }).collect();
+            (vertices, mesh.triangles)
+        }
+        MeshFormat::Obj | MeshFormat::ObjColors => parse_obj(content)?,
+        format => {
+            return Err(Error::invalid_parameter("mesh", format!("a {} mesh can not be read, only json and obj", format)));
+        }
+    };
+
+    let vertex = |index: usize| vertices.get(index).copied().ok_or_else(|| Error::invalid_parameter(
+        "mesh",
+        format!("the vertex {} does not exist, the mesh has {} vertices", index, vertices.len()),
+    ));
+    let triangles = faces.iter()
+        .map(|[a, b, c]| Ok(Triangle::new(vertex(*a)?, vertex(*b)?, vertex(*c)?)))
+        .collect::<Result<Vec<_>>>()?;
+    if let Some(point) = vertices.iter().find(|point| !(point.x.is_finite() && point.y.is_finite())) {
+        return Err(Error::invalid_parameter("mesh", format!("invalid vertex ({}, {})", point.x, point.y)));
+    }
+    if triangles.is_empty() {
+        return Err(Error::DegenerateGeometry("the mesh does not contain any triangle".to_owned()));
+    }
+    Ok(triangles)
+}
+
+/// Reads the `v` and `f` lines of an obj file, the faces are 0-based triangles.
+fn parse_obj(content: &str) -> Result<(Vec<Point>, Vec<[usize; 3]>)> {
+    let invalid_line = |number: usize, line: &str| {
+        Error::invalid_parameter("mesh", format!("invalid line `{}` on line {}", line, number))
+    };
+
+    let (mut vertices, mut faces) = (vec![], vec![]);
+    for (index, line) in content.lines().enumerate() {
+        let (number, line) = (index + 1, line.trim());
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => {
+                let mut coordinates = tokens.map(|token| token.parse::<f64>());
+                match (coordinates.next(), coordinates.next()) {
+                    (Some(Ok(x)), Some(Ok(y))) => vertices.push(Point { x, y }),
+                    _ => return Err(invalid_line(number, line)),
+                }
+            }
+            Some("f") => {
+                // `f 1 2 3`, `f 1/1 2/2 3/3` or `f -3 -2 -1`, relative to the vertices read so far
+                let indices = tokens
+                    .map(|token| {
+                        let index: i64 = token.split('/').next()?.parse().ok()?;
+                        match index {
+                            0 => None,
+                            index if index > 0 => Some(index as usize - 1),
+                            index => vertices.len().checked_sub(index.unsigned_abs() as usize),
+                        }
+                    })
+                    .collect::<Option<Vec<usize>>>()
+                    .filter(|indices| indices.len() >= 3)
+                    .ok_or_else(|| invalid_line(number, line))?;
+                faces.extend((1..indices.len() - 1).map(|i| [indices[0], indices[i], indices[i + 1]]));
+            }
+            _ => {}
+        }
+    }
+    Ok((vertices, faces))
+}
+
+
+#[cfg(test)]
+mod test {
+    use resvg::usvg;
+
+    use super::{parse_triangles, Face, Mesh, MeshFormat};
+    use crate::delaunay::Point;
+    use crate::drawer::{Drawing, Paint, Shape, Stroke};
+    use crate::error::Error;
+    use crate::error::test::assert_invalid;
+
+    /// Two triangles sharing an edge, and a shape without fill nor stroke.
+    fn drawing() -> Drawing {
+        let [a, b, c, d] = [(0.0, 0.0), (4.0, 0.0), (4.0, 2.0), (0.0, 2.0)].map(|(x, y)| Point { x, y });
+        let red = Paint { color: usvg::Color::new_rgb(255, 0, 0), opacity: 1.0 };
+        let blue = Paint { color: usvg::Color::new_rgb(0, 0, 255), opacity: 0.5 };
+        let shapes = vec![
+            Shape { vertex: [a, b, c], fill: Some(red), stroke: None },
+            Shape { vertex: [a, c, d], fill: None, stroke: Some(Stroke { paint: blue, width: 1.0 }) },
+            Shape { vertex: [a, b, d], fill: None, stroke: None },
+        ];
+        Drawing::new(4.0, 2.0, None, shapes)
+    }
+
+    fn write(mesh: &Mesh, format: MeshFormat) -> String {
+        let mut bytes = vec![];
+        mesh.write(&mut bytes, format).unwrap();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn test_from_drawing() {
+        let mesh = Mesh::from_drawing(&drawing());
+        assert_eq!((mesh.width, mesh.height), (4.0, 2.0));
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.faces.iter().map(|face| face.indices).collect::<Vec<_>>(), vec![[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(mesh.faces[1].color.opacity, 0.5);
+    }
+
+    #[test]
+    fn test_write() {
+        let mesh = Mesh::from_drawing(&drawing());
+
+        let json: serde_json::Value = serde_json::from_str(&write(&mesh, MeshFormat::Json)).unwrap();
+        assert_eq!(json["vertices"][2], serde_json::json!([4.0, 2.0]));
+        assert_eq!(json["triangles"], serde_json::json!([[0, 1, 2], [0, 2, 3]]));
+        assert_eq!(json["colors"], serde_json::json!([[255, 0, 0, 255], [0, 0, 255, 128]]));
+
+        let obj = write(&mesh, MeshFormat::Obj);
+        assert_eq!(obj.lines().filter(|line| line.starts_with("v ")).count(), 4);
+        assert!(obj.contains("usemtl color_ff0000ff\nf 1 2 3\nusemtl color_0000ff80\nf 1 3 4\n"));
+        let mut mtl = vec![];
+        mesh.write_mtl(&mut mtl).unwrap();
+        assert!(String::from_utf8(mtl).unwrap().contains("newmtl color_0000ff80\nKd 0.0000 0.0000 1.0000\nd 0.5020\n"));
+
+        let obj = write(&mesh, MeshFormat::ObjColors);
+        assert!(obj.starts_with("v 0 0 0 1.0000 0.0000 0.0000\n"));
+        assert!(obj.ends_with("f 1 2 3\nf 4 5 6\n"));
+
+        let ply = write(&mesh, MeshFormat::Ply);
+        assert!(ply.contains("element vertex 4\n"));
+        assert!(ply.contains("element face 2\n"));
+        assert!(ply.ends_with("end_header\n0 0 0\n4 0 0\n4 2 0\n0 2 0\n3 0 1 2 255 0 0 255\n3 0 2 3 0 0 255 128\n"));
+
+        let geojson: serde_json::Value = serde_json::from_str(&write(&mesh, MeshFormat::GeoJson)).unwrap();
+        assert_eq!(geojson["features"].as_array().unwrap().len(), 2);
+        assert_eq!(geojson["features"][0]["geometry"]["coordinates"], serde_json::json!([[[0.0, 0.0], [4.0, 0.0], [4.0, 2.0], [0.0, 0.0]]]));
+        assert_eq!(geojson["features"][1]["properties"]["fill"], "#0000ff");
+
+        // the clockwise triangles are reversed
+        let clockwise = Mesh { faces: mesh.faces.iter().map(|face| Face { indices: [0, 2, 1], ..*face }).collect(), ..mesh };
+        let geojson: serde_json::Value = serde_json::from_str(&write(&clockwise, MeshFormat::GeoJson)).unwrap();
+        assert_eq!(geojson["features"][0]["geometry"]["coordinates"], serde_json::json!([[[0.0, 0.0], [4.0, 0.0], [4.0, 2.0], [0.0, 0.0]]]));
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(MeshFormat::from_path("mesh.GeoJSON"), Some(MeshFormat::GeoJson));
+        assert_eq!(MeshFormat::from_path("mesh.obj"), Some(MeshFormat::Obj));
+        assert_eq!(MeshFormat::from_path("mesh.png"), None);
+        assert_eq!("obj-colors".parse::<MeshFormat>(), Ok(MeshFormat::ObjColors));
+        assert_eq!(MeshFormat::Ply.to_string(), "ply");
+        assert!("stl".parse::<MeshFormat>().is_err());
+    }
+
+    #[test]
+    fn test_parse_triangles() {
+        let mesh = Mesh::from_drawing(&drawing());
+        let vertices = |format, width, height| -> Vec<[Point; 3]> {
+            let mut bytes = vec![];
+            mesh.write(&mut bytes, format).unwrap();
+            let content = String::from_utf8(bytes).unwrap();
+            parse_triangles(&content, format, width, height).unwrap().iter().map(|triangle| triangle.vertex()).collect()
+        };
+        let expected: Vec<_> = mesh.faces.iter().map(|face| face.indices.map(|index| mesh.vertices[index])).collect();
+
+        assert_eq!(vertices(MeshFormat::Json, 4, 2), expected);
+        assert_eq!(vertices(MeshFormat::Obj, 4, 2), expected);
+        assert_eq!(vertices(MeshFormat::ObjColors, 4, 2), expected);
+        // a json mesh is scaled to the image
+        assert_eq!(vertices(MeshFormat::Json, 8, 2)[0][1], Point { x: 8.0, y: 0.0 });
+
+        // the polygons are split into triangles, the negative indices are relative
+        let quad = "v 0 0 0\nv 4 0 0\nv 4 2 0\nv 0 2 0\nf -4/1 -3/2 -2/3 -1/4\n";
+        assert_eq!(parse_triangles(quad, MeshFormat::Obj, 4, 2).unwrap().len(), 2);
+
+        let test_cases = [
+            ("v 0 0\nf 1 2 3\n", MeshFormat::Obj),
+            ("v 0 0\nf 1 1\n", MeshFormat::Obj),
+            (r#"{"vertices": [[0, 0]], "triangles": [[0, 0, 1]]}"#, MeshFormat::Json),
+            ("{}", MeshFormat::Json),
+            ("ply", MeshFormat::Ply),
+        ];
+        for (content, format) in test_cases {
+            assert_invalid(parse_triangles(content, format, 4, 2), "mesh");
+        }
+        assert!(matches!(parse_triangles("v 0 0\n", MeshFormat::Obj, 4, 2), Err(Error::DegenerateGeometry(_))));
+    }
+}
@@ -0,0 +1,310 @@
+use std::collections::{HashMap, HashSet};
+
+use image::{imageops, RgbaImage};
+use resvg::usvg;
+
+use crate::animation::Timing;
+use crate::delaunay::{Delaunay, Point, Triangle};
+use crate::drawer::{Drawer, Drawing, Paint, Shape, Stroke};
+use crate::error::{Error, Result};
+
+
+/// Transition between two images: the triangulation of the source image moves and changes its
+/// colors until it matches the target image.
+///
+/// Both images share the same mesh: the points of the target are resampled to the number of points
+/// of the source, each point of the source is paired with the nearest point of the target, and the
+/// triangles of the source are kept over the whole transition. The corners and the points on the
+/// border of the image stay in place, so the mesh always covers the image.
+///
+/// The pairing does not prevent the triangles from crossing each other: a triangle whose points
+/// move past each other folds over its neighbours in the middle of the transition, before
+/// unfolding by the last frame.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Morph {
+    /// the frames go from the source image to the target image, at least 2
+    pub timing: Timing,
+}
+
+/// Removes the duplicated points, keeping their first occurrence.
+fn unique(points: Vec<Point>) -> Vec<Point> {
+    let mut seen = HashSet::new();
+    points.into_iter()
+        .filter(|point| seen.insert((point.x.to_bits(), point.y.to_bits())))
+        .collect()
+}
+
+/// Picks `count` points evenly spread in `points`.
+fn resample(points: &[Point], count: usize) -> Vec<Point> {
+    (0..count).map(|i| points[i * points.len() / count]).collect()
+}
+
+/// Pairs each point of `from` with the nearest point of `to` not paired yet, returns the partners
+/// in the order of `from`.
+fn match_points(from: &[Point], to: &[Point]) -> Vec<Point> {
+    let mut available = to.to_vec();
+    from.iter()
+        .filter_map(|point| {
+            let distance = |other: &Point| (other.x - point.x).powi(2) + (other.y - point.y).powi(2);
+            let (nearest, _) = available.iter()
+                .enumerate()
+                .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))?;
+            Some(available.swap_remove(nearest))
+        })
+        .collect()
+}
+
+fn mix(from: f64, to: f64, t: f64) -> f64 {
+    from + (to - from) * t
+}
+
+fn mix_paint(from: Paint, to: Paint, t: f64) -> Paint {
+    let channel = |from: u8, to: u8| mix(from as f64, to as f64, t).round() as u8;
+    Paint {
+        color: usvg::Color::new_rgb(
+            channel(from.color.red, to.color.red),
+            channel(from.color.green, to.color.green),
+            channel(from.color.blue, to.color.blue),
+        ),
+        opacity: mix(from.opacity, to.opacity, t),
+    }
+}
+
+/// Interpolates the paints of two shapes, a shape or a paint missing on one side fades in or out.
+fn mix_shapes(from: Option<Shape>, to: Option<Shape>, t: f64) -> Option<Shape> {
+    let fade = |paint: Paint, factor: f64| Paint { opacity: paint.opacity * factor, ..paint };
+    let mix_paints = |from: Option<Paint>, to: Option<Paint>| match (from, to) {
+        (Some(from), Some(to)) => Some(mix_paint(from, to, t)),
+        (Some(from), None) => Some(fade(from, 1.0 - t)),
+        (None, Some(to)) => Some(fade(to, t)),
+        (None, None) => None,
+    };
+
+    let (vertex, from_fill, from_stroke) = match from {
+        Some(shape) => (shape.vertex, shape.fill, shape.stroke),
+        None => (to?.vertex, None, None),
+    };
+    let (to_fill, to_stroke) = to.map_or((None, None), |shape| (shape.fill, shape.stroke));
+    let width = match (from_stroke, to_stroke) {
+        (Some(from), Some(to)) => mix(from.width, to.width, t),
+        (from, to) => from.or(to).map_or(0.0, |stroke| stroke.width),
+    };
+    Some(Shape {
+        vertex,
+        fill: mix_paints(from_fill, to_fill),
+        stroke: mix_paints(from_stroke.map(|stroke| stroke.paint), to_stroke.map(|stroke| stroke.paint))
+            .map(|paint| Stroke { paint, width }),
+    })
+}
+
+impl Morph {
+
+    /// Checks that every parameter is in its valid range.
+    pub fn validate(&self) -> Result<()> {
+        self.timing.validate(2)
+    }
+
+    /// Checks that `drawer` can draw the frames: its parameters are valid and it has no vertex
+    /// markers, which the frames do not draw.
+    pub fn validate_drawer(drawer: &Drawer) -> Result<()> {
+        drawer.validate()?;
+        if drawer.vertex_radius > 0.0 {
+            return Err(Error::invalid_parameter("vertex_radius", "the morph frames cannot draw vertex markers"));
+        }
+        Ok(())
+    }
+
+    /// Builds the drawing of each frame, from the points sampled on both images. The target image
+    /// is stretched to the size of the source image, see [`Morph::validate_drawer`].
+    pub fn drawings(
+        &self,
+        drawer: &Drawer,
+        source: &RgbaImage,
+        source_points: Vec<Point>,
+        target: &RgbaImage,
+        target_points: Vec<Point>,
+    ) -> Result<Vec<Drawing>> {
+        self.validate()?;
+        Self::validate_drawer(drawer)?;
+        let (width, height) = source.dimensions();
+        let (target_width, target_height) = target.dimensions();
+        if width == 0 || height == 0 || target_width == 0 || target_height == 0 {
+            return Err(Error::DegenerateGeometry("the source or the target image is empty".to_owned()));
+        }
+
+        let (scale_x, scale_y) = (width as f64 / target_width as f64, height as f64 / target_height as f64);
+        let target = if target.dimensions() == source.dimensions() {
+            target.clone()
+        } else {
+            imageops::resize(target, width, height, imageops::FilterType::Triangle)
+        };
+        let target_points = target_points.into_iter()
+            .map(|point| Point { x: point.x * scale_x, y: point.y * scale_y })
+            .collect();
+
+        let (source_points, target_points) = (unique(source_points), unique(target_points));
+        let count = source_points.len().min(target_points.len());
+        if count == 0 {
+            return Err(Error::NoPoints);
+        }
+        let mut from = resample(&source_points, count);
+
+        // the points on the border of the image stay there, the mesh is not pulled inward
+        let (width, height) = (width as f64, height as f64);
+        let on_border = |point: &Point| point.x == 0.0 || point.y == 0.0 || point.x == width || point.y == height;
+        let inner: Vec<Point> = from.iter().filter(|point| !on_border(point)).copied().collect();
+        let mut partners = match_points(&inner, &resample(&target_points, count)).into_iter();
+        let mut to: Vec<Point> = from.iter()
+            .map(|point| if on_border(point) { *point } else { partners.next().unwrap_or(*point) })
+            .collect();
+
+        // the corners of the image, used by the triangulation, stay in place
+        let corners = [(0.0, 0.0), (width, 0.0), (width, height), (0.0, height)].map(|(x, y)| Point { x, y });
+        from.extend(corners);
+        to.extend(corners);
+
+        let mut delaunay = Delaunay::new(height, width);
+        delaunay.add_points(from.clone());
+        let mut indices = HashMap::new();
+        for (index, point) in from.iter().enumerate() {
+            indices.entry((point.x.to_bits(), point.y.to_bits())).or_insert(index);
+        }
+        let mesh: Vec<[usize; 3]> = delaunay.triangles()
+            .iter()
+            .filter_map(|triangle| {
+                let [a, b, c] = triangle.vertex().map(|point| indices.get(&(point.x.to_bits(), point.y.to_bits())).copied());
+                Some([a?, b?, c?])
+            })
+            .collect();
+        if mesh.is_empty() {
+            return Err(Error::DegenerateGeometry("the triangulation did not produce any triangle".to_owned()));
+        }
+
+        let triangles_at = |points: &[Point]| -> Vec<Triangle> {
+            mesh.iter().map(|[a, b, c]| Triangle::new(points[*a], points[*b], points[*c])).collect()
+        };
+        let from_shapes = drawer.shapes(source, &triangles_at(&from));
+        let to_shapes = drawer.shapes(&target, &triangles_at(&to));
+
+        let drawings = (0..self.timing.frames)
+            .map(|frame| {
+                let t = frame as f64 / (self.timing.frames - 1) as f64;
+                let shapes = mesh.iter()
+                    .zip(from_shapes.iter().zip(&to_shapes))
+                    .filter_map(|(indices, (from_shape, to_shape))| {
+                        let vertex = indices.map(|i| Point { x: mix(from[i].x, to[i].x, t), y: mix(from[i].y, to[i].y, t) });
+                        mix_shapes(*from_shape, *to_shape, t).map(|shape| Shape { vertex, ..shape })
+                    })
+                    .collect();
+                Drawing::new(width, height, drawer.background(), shapes)
+                    .with_backend(drawer.backend)
+                    .with_seamless(drawer.seamless)
+            })
+            .collect();
+        Ok(drawings)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use image::{Rgba, RgbaImage};
+
+    use resvg::usvg;
+
+    use super::{match_points, mix_shapes, resample, Morph};
+    use crate::animation::Timing;
+    use crate::delaunay::Point;
+    use crate::drawer::{Drawer, Paint, Shape, Stroke};
+    use crate::error::Error;
+    use crate::error::test::assert_invalid;
+
+    #[test]
+    fn test_match_points() {
+        let from = [Point { x: 0.0, y: 0.0 }, Point { x: 10.0, y: 10.0 }];
+        let to = [Point { x: 11.0, y: 9.0 }, Point { x: 1.0, y: 1.0 }];
+        assert_eq!(match_points(&from, &to), vec![to[1], to[0]]);
+
+        let points: Vec<Point> = (0..10).map(|i| Point { x: i as f64, y: 0.0 }).collect();
+        assert_eq!(resample(&points, 3), vec![points[0], points[3], points[6]]);
+        assert_eq!(resample(&points, 10), points);
+    }
+
+    #[test]
+    fn test_mix_shapes() {
+        let paint = Paint { color: usvg::Color::new_rgb(0, 0, 255), opacity: 1.0 };
+        let stroke = Stroke { paint: Paint { color: usvg::Color::black(), opacity: 1.0 }, width: 1.0 };
+        let vertex = [Point { x: 0.0, y: 0.0 }, Point { x: 1.0, y: 0.0 }, Point { x: 0.0, y: 1.0 }];
+
+        // the fill missing over a transparent area fades in, the stroke on both sides is kept
+        let from = Shape { vertex, fill: None, stroke: Some(stroke) };
+        let to = Shape { vertex, fill: Some(paint), stroke: Some(stroke) };
+        let shape = mix_shapes(Some(from), Some(to), 0.25).unwrap();
+        assert_eq!(shape.fill, Some(Paint { opacity: 0.25, ..paint }));
+        assert_eq!(shape.stroke, Some(stroke));
+
+        let shape = mix_shapes(None, Some(to), 0.5).unwrap();
+        assert_eq!(shape.stroke.unwrap().paint.opacity, 0.5);
+        assert_eq!(mix_shapes(None, None, 0.5), None);
+    }
+
+    #[test]
+    fn test_drawings() {
+        let source = RgbaImage::from_pixel(40, 30, Rgba::from([255, 0, 0, 255]));
+        let target = RgbaImage::from_pixel(80, 60, Rgba::from([0, 0, 255, 255]));
+        let mut source_points: Vec<Point> = (0..12).map(|i| Point { x: (i * 7 % 40) as f64, y: (i * 11 % 30) as f64 }).collect();
+        let border = [Point { x: 0.0, y: 15.0 }, Point { x: 20.0, y: 0.0 }];
+        source_points.extend(border);
+        let target_points: Vec<Point> = (0..20).map(|i| Point { x: (i * 13 % 80) as f64, y: (i * 17 % 60) as f64 }).collect();
+
+        let morph = Morph { timing: Timing { frames: 3, ..Default::default() } };
+        let drawer = Drawer { stroke_width: 0.0, ..Default::default() };
+        let drawings = morph.drawings(&drawer, &source, source_points.clone(), &target, target_points).unwrap();
+        assert_eq!(drawings.len(), 3);
+
+        let fill = |frame: usize| {
+            let paint = drawings[frame].shapes()[0].fill.unwrap();
+            [paint.color.red, paint.color.green, paint.color.blue]
+        };
+        assert_eq!(fill(0), [255, 0, 0]);
+        assert_eq!(fill(1), [128, 0, 128]);
+        assert_eq!(fill(2), [0, 0, 255]);
+
+        // the first frame is the triangulation of the source points, the mesh is kept over the frames
+        for shape in drawings[0].shapes() {
+            assert!(shape.vertex.iter().all(|vertex| {
+                source_points.contains(vertex) || [0.0, 40.0].contains(&vertex.x) && [0.0, 30.0].contains(&vertex.y)
+            }));
+        }
+        assert!(drawings.iter().all(|drawing| drawing.shapes().len() == drawings[0].shapes().len()));
+        assert!(drawings.iter().all(|drawing| drawing.size() == (40.0, 30.0)));
+        assert!(drawings[0].shapes().iter().zip(drawings[2].shapes()).any(|(from, to)| from.vertex != to.vertex));
+
+        // the points on the border do not move
+        for drawing in &drawings {
+            assert!(border.iter().all(|point| drawing.shapes().iter().any(|shape| shape.vertex.contains(point))));
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let test_cases = vec![
+            ("frames", Morph { timing: Timing { frames: 1, ..Default::default() } }),
+            ("delay", Morph { timing: Timing { delay: 0, ..Default::default() } }),
+            ("hold", Morph { timing: Timing { hold: 0, ..Default::default() } }),
+        ];
+
+        for (expected, morph) in test_cases {
+            assert_invalid(morph.validate(), expected);
+        }
+        assert!(Morph::default().validate().is_ok());
+
+        let image = RgbaImage::new(10, 10);
+        let result = Morph::default().drawings(&Drawer::default(), &image, vec![], &image, vec![]);
+        assert!(matches!(result, Err(Error::NoPoints)));
+
+        let markers = Drawer { vertex_radius: 2.0, only_points: true, ..Default::default() };
+        let points = vec![Point { x: 2.0, y: 2.0 }];
+        assert_invalid(Morph::default().drawings(&markers, &image, points.clone(), &image, points), "vertex_radius");
+    }
+}
@@ -0,0 +1,346 @@
+use std::fs;
+use std::path::Path;
+
+use image::{Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::color::Color;
+use crate::error::{Error, Result};
+
+
+/// Largest number of colors extracted from the source image.
+pub const MAX_SIZE: usize = 256;
+
+/// Largest number of pixels of the source image used to extract a palette.
+const MAX_SAMPLES: usize = 1 << 14;
+
+/// Colors the triangle fills are snapped to, see [`Quantizer`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Palette {
+    /// a fixed list of colors
+    Colors(Vec<Color>),
+    /// colors extracted from the source image with the median cut algorithm
+    MedianCut(usize),
+    /// colors extracted from the source image with the k-means clustering
+    KMeans(usize),
+}
+
+impl Palette {
+
+    /// Reads a palette file, see [`Palette::parse`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses a GIMP palette (`.gpl`) or a list of colors, one per line in any syntax supported
+    /// by [`Color`]. Empty lines and lines starting with `//` are ignored.
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut lines = content.lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty())
+            .peekable();
+
+        let is_gpl = lines.peek().map(|(_, line)| *line == "GIMP Palette").unwrap_or(false);
+        let colors = if is_gpl {
+            lines.skip(1)
+                .filter(|(_, line)| !(line.starts_with('#') || line.starts_with("Name:") || line.starts_with("Columns:")))
+                .map(|(number, line)| parse_gpl_line(line).ok_or_else(|| invalid_line(number, line)))
+                .collect::<Result<Vec<_>>>()?
+        } else {
+            lines.filter(|(_, line)| !line.starts_with("//"))
+                .map(|(number, line)| line.parse::<Color>().map_err(|_| invalid_line(number, line)))
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        let palette = Palette::Colors(colors);
+        palette.validate()?;
+        Ok(palette)
+    }
+
+    /// Checks that the palette has at least one color.
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Palette::Colors(colors) if colors.is_empty() => {
+                Err(Error::invalid_parameter("palette", "the palette does not contain any color"))
+            }
+            Palette::MedianCut(size) | Palette::KMeans(size) if !(1..=MAX_SIZE).contains(size) => {
+                Err(Error::invalid_parameter(
+                    "palette",
+                    format!("the number of colors must be between 1 and {}, got {}", MAX_SIZE, size),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the colors of the palette, extracting them from `source_image` if needed.
+    pub fn colors(&self, source_image: &RgbaImage) -> Vec<Color> {
+        match self {
+            Palette::Colors(colors) => colors.clone(),
+            Palette::MedianCut(size) => median_cut(source_image, *size),
+            Palette::KMeans(size) => k_means(source_image, *size),
+        }
+    }
+}
+
+fn invalid_line(number: usize, line: &str) -> Error {
+    Error::invalid_parameter("palette", format!("invalid color `{}` on line {}", line, number))
+}
+
+/// Parses a `R G B name` line of a GIMP palette.
+fn parse_gpl_line(line: &str) -> Option<Color> {
+    let mut channels = line.split_whitespace().map(|channel| channel.parse::<u8>());
+    match (channels.next()?, channels.next()?, channels.next()?) {
+        (Ok(red), Ok(green), Ok(blue)) => Some(Color::new_rgb(red, green, blue)),
+        _ => None,
+    }
+}
+
+
+/// A color in the CIELAB space, where the euclidean distance follows the perceived difference.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lab {
+    pub l: f64,
+    pub a: f64,
+    pub b: f64,
+}
+
+impl Lab {
+
+    /// Converts a sRGB color, under the D65 illuminant.
+    pub fn from_rgb(red: u8, green: u8, blue: u8) -> Self {
+        let linear = |channel: u8| {
+            let channel = channel as f64 / 255.0;
+            if channel <= 0.04045 { channel / 12.92 } else { ((channel + 0.055) / 1.055).powf(2.4) }
+        };
+        let (r, g, b) = (linear(red), linear(green), linear(blue));
+
+        let x = (0.4124564 * r + 0.3575761 * g + 0.1804375 * b) / 0.95047;
+        let y = 0.2126729 * r + 0.7151522 * g + 0.0721750 * b;
+        let z = (0.0193339 * r + 0.1191920 * g + 0.9503041 * b) / 1.08883;
+
+        let f = |t: f64| if t > 216.0 / 24389.0 { t.cbrt() } else { (24389.0 / 27.0 * t + 16.0) / 116.0 };
+        let (fx, fy, fz) = (f(x), f(y), f(z));
+        Self { l: 116.0 * fy - 16.0, a: 500.0 * (fx - fy), b: 200.0 * (fy - fz) }
+    }
+
+    /// Squared euclidean distance (CIE76) between two colors.
+    pub fn distance_squared(&self, other: &Lab) -> f64 {
+        (self.l - other.l).powi(2) + (self.a - other.a).powi(2) + (self.b - other.b).powi(2)
+    }
+}
+
+impl From<Color> for Lab {
+
+    fn from(color: Color) -> Self {
+        Self::from_rgb(color.red, color.green, color.blue)
+    }
+}
+
+
+/// Snaps colors to the nearest color of a palette, compared in the CIELAB space.
+pub struct Quantizer {
+    colors: Vec<(Color, Lab)>,
+}
+
+impl Quantizer {
+
+    pub fn new(colors: &[Color]) -> Self {
+        Self { colors: colors.iter().map(|color| (*color, Lab::from(*color))).collect() }
+    }
+
+    /// Returns the nearest color of the palette, keeping the alpha of `pixel`.
+    pub fn nearest(&self, pixel: Rgba<u8>) -> Rgba<u8> {
+        let lab = Lab::from_rgb(pixel[0], pixel[1], pixel[2]);
+        self.colors.iter()
+            .min_by(|(_, a), (_, b)| a.distance_squared(&lab).total_cmp(&b.distance_squared(&lab)))
+            .map(|(color, _)| Rgba::from([color.red, color.green, color.blue, pixel[3]]))
+            .unwrap_or(pixel)
+    }
+}
+
+
+/// Returns the colors of the non transparent pixels, evenly skipping pixels on large images.
+fn sample_pixels(image: &RgbaImage) -> Vec<[u8; 3]> {
+    let pixels: Vec<[u8; 3]> = image.pixels()
+        .filter(|pixel| pixel[3] > 0)
+        .map(|pixel| [pixel[0], pixel[1], pixel[2]])
+        .collect();
+    let step = pixels.len().div_ceil(MAX_SAMPLES).max(1);
+    pixels.into_iter().step_by(step).collect()
+}
+
+fn average(pixels: &[[u8; 3]]) -> Color {
+    let mut sum = [0u64; 3];
+    for pixel in pixels {
+        for channel in 0..3 {
+            sum[channel] += pixel[channel] as u64;
+        }
+    }
+    let count = pixels.len().max(1) as u64;
+    let mean = |channel: usize| ((sum[channel] + count / 2) / count) as u8;
+    Color::new_rgb(mean(0), mean(1), mean(2))
+}
+
+/// Extracts up to `size` colors from the image with the median cut algorithm: the box of pixels
+/// with the widest channel range is split at its median until there are `size` boxes.
+pub fn median_cut(image: &RgbaImage, size: usize) -> Vec<Color> {
+    median_cut_pixels(sample_pixels(image), size)
+}
+
+fn median_cut_pixels(pixels: Vec<[u8; 3]>, size: usize) -> Vec<Color> {
+    if pixels.is_empty() {
+        return vec![];
+    }
+
+    let widest_channel = |pixels: &[[u8; 3]]| {
+        (0..3)
+            .map(|channel| {
+                let (min, max) = pixels.iter().fold((u8::MAX, u8::MIN), |(min, max), pixel| {
+                    (min.min(pixel[channel]), max.max(pixel[channel]))
+                });
+                (channel, max - min)
+            })
+            .max_by_key(|(_, range)| *range)
+            .unwrap_or((0, 0))
+    };
+
+    let mut boxes = vec![pixels];
+    while boxes.len() < size {
+        let widest = boxes.iter().enumerate()
+            .map(|(index, pixels)| (index, widest_channel(pixels)))
+            .max_by_key(|(_, (_, range))| *range);
+        let Some((index, (channel, range))) = widest else {
+            break;
+        };
+        if range == 0 {
+            break;
+        }
+
+        let mut lower = boxes.remove(index);
+        lower.sort_unstable_by_key(|pixel| pixel[channel]);
+        let upper = lower.split_off(lower.len() / 2);
+        boxes.push(lower);
+        boxes.push(upper);
+    }
+
+    boxes.iter().map(|pixels| average(pixels)).collect()
+}
+
+/// Extracts up to `size` colors from the image with the k-means clustering in the CIELAB space,
+/// starting from the median cut colors.
+pub fn k_means(image: &RgbaImage, size: usize) -> Vec<Color> {
+    const ITERATIONS: usize = 16;
+
+    let pixels = sample_pixels(image);
+    let labs: Vec<Lab> = pixels.iter().map(|pixel| Lab::from_rgb(pixel[0], pixel[1], pixel[2])).collect();
+    let mut centroids = median_cut_pixels(pixels.clone(), size);
+
+    for _ in 0..ITERATIONS {
+        let centroid_labs: Vec<Lab> = centroids.iter().map(|color| Lab::from(*color)).collect();
+        let mut clusters: Vec<Vec<[u8; 3]>> = vec![vec![]; centroids.len()];
+        for (pixel, lab) in pixels.iter().zip(&labs) {
+            let nearest = (0..centroid_labs.len())
+                .min_by(|a, b| centroid_labs[*a].distance_squared(lab).total_cmp(&centroid_labs[*b].distance_squared(lab)))
+                .unwrap_or(0);
+            clusters[nearest].push(*pixel);
+        }
+
+        let updated: Vec<Color> = clusters.iter()
+            .zip(&centroids)
+            .map(|(cluster, centroid)| if cluster.is_empty() { *centroid } else { average(cluster) })
+            .collect();
+        if updated == centroids {
+            break;
+        }
+        centroids = updated;
+    }
+    centroids
+}
+
+
+#[cfg(test)]
+mod test {
+    use image::{Rgba, RgbaImage};
+
+    use super::{k_means, median_cut, Lab, Palette, Quantizer};
+    use crate::color::Color;
+
+    fn two_colors_image() -> RgbaImage {
+        RgbaImage::from_fn(20, 10, |x, y| {
+            let noise = ((x + y) % 3) as u8;
+            if x < 10 { Rgba::from([200 + noise, 20, 20, 255]) } else { Rgba::from([20, 20, 200 + noise, 255]) }
+        })
+    }
+
+    #[test]
+    fn test_parse_list() {
+        let palette = Palette::parse("// brand colors\n#ff0000\n\n  tomato  \nrgb(0, 0, 255)\n").unwrap();
+        assert_eq!(palette, Palette::Colors(vec![
+            Color::new_rgb(255, 0, 0),
+            Color::new_rgb(255, 99, 71),
+            Color::new_rgb(0, 0, 255),
+        ]));
+
+        assert!(Palette::parse("#ff0000\nnotacolor").is_err());
+        assert!(Palette::parse("// nothing\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_gpl() {
+        let content = "GIMP Palette\nName: Brand\nColumns: 2\n#\n255   0   0\tRed\n  0 128 255 Azure\n";
+        assert_eq!(Palette::parse(content).unwrap(), Palette::Colors(vec![
+            Color::new_rgb(255, 0, 0),
+            Color::new_rgb(0, 128, 255),
+        ]));
+
+        assert!(Palette::parse("GIMP Palette\n300 0 0 Invalid").is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(Palette::KMeans(8).validate().is_ok());
+        assert!(Palette::MedianCut(0).validate().is_err());
+        assert!(Palette::KMeans(1000).validate().is_err());
+        assert!(Palette::Colors(vec![]).validate().is_err());
+    }
+
+    #[test]
+    fn test_lab() {
+        let white = Lab::from_rgb(255, 255, 255);
+        assert!((white.l - 100.0).abs() < 0.01 && white.a.abs() < 0.01 && white.b.abs() < 0.01);
+
+        let red = Lab::from_rgb(255, 0, 0);
+        assert!((red.l - 53.24).abs() < 0.01 && (red.a - 80.09).abs() < 0.01 && (red.b - 67.20).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_quantizer() {
+        let quantizer = Quantizer::new(&[Color::new_rgb(0, 0, 0), Color::new_rgb(255, 255, 255), Color::new_rgb(255, 0, 0)]);
+        assert_eq!(quantizer.nearest(Rgba::from([30, 30, 30, 128])), Rgba::from([0, 0, 0, 128]));
+        assert_eq!(quantizer.nearest(Rgba::from([200, 60, 50, 255])), Rgba::from([255, 0, 0, 255]));
+        assert_eq!(quantizer.nearest(Rgba::from([230, 220, 220, 255])), Rgba::from([255, 255, 255, 255]));
+
+        let empty = Quantizer::new(&[]);
+        assert_eq!(empty.nearest(Rgba::from([1, 2, 3, 4])), Rgba::from([1, 2, 3, 4]));
+    }
+
+    #[test]
+    fn test_extract_palette() {
+        let image = two_colors_image();
+
+        for colors in [median_cut(&image, 2), k_means(&image, 2)] {
+            assert_eq!(colors.len(), 2);
+            assert!(colors.iter().any(|color| color.red > 190 && color.blue < 30));
+            assert!(colors.iter().any(|color| color.blue > 190 && color.red < 30));
+        }
+
+        // an uniform image gives a single color whatever the requested size
+        let uniform = RgbaImage::from_pixel(8, 8, Rgba::from([10, 20, 30, 255]));
+        assert_eq!(median_cut(&uniform, 16), vec![Color::new_rgb(10, 20, 30)]);
+        assert_eq!(k_means(&uniform, 16), vec![Color::new_rgb(10, 20, 30)]);
+        assert!(median_cut(&RgbaImage::new(4, 4), 4).is_empty());
+    }
+}
@@ -0,0 +1,217 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::delaunay::Point;
+use crate::error::{Error, Result};
+
+
+/// How the points of a [`PointSet`] are combined with the points sampled on the edges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PointsMode {
+    /// the points are added to the sampled points
+    #[default]
+    Merge,
+    /// only the points are triangulated, the edge detection is skipped
+    Replace,
+}
+
+impl fmt::Display for PointsMode {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PointsMode::Merge => f.pad("merge"),
+            PointsMode::Replace => f.pad("replace"),
+        }
+    }
+}
+
+impl FromStr for PointsMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "merge" => Ok(PointsMode::Merge),
+            "replace" => Ok(PointsMode::Replace),
+            _ => Err(format!("unknown points mode `{}` (expected merge or replace)", value)),
+        }
+    }
+}
+
+/// File format of a [`PointSet`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointsFormat {
+    /// a `x,y` point per line, separated by commas, semicolons or spaces
+    Csv,
+    /// a list of `[x, y]` or `{ "x": .., "y": .. }`, or an object with such a list in `points`
+    /// or `vertices` (a mesh exported as json)
+    Json,
+}
+
+impl PointsFormat {
+
+    /// Guess the format from the extension of `path`, `.json` is read as json and any other file
+    /// as csv.
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => PointsFormat::Json,
+            _ => PointsFormat::Csv,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonPoint {
+    Pair([f64; 2]),
+    Object { x: f64, y: f64 },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonPoints {
+    List(Vec<JsonPoint>),
+    Points { points: Vec<JsonPoint> },
+    Vertices { vertices: Vec<JsonPoint> },
+}
+
+/// Points placed by hand or computed by another tool, in pixels of the source image.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PointSet {
+    pub points: Vec<Point>,
+    pub mode: PointsMode,
+}
+
+impl PointSet {
+
+    /// Reads a points file, its format is guessed from its extension.
+    pub fn load(path: impl AsRef<Path>, mode: PointsMode) -> Result<Self> {
+        let format = PointsFormat::from_path(&path);
+        Self::parse(&fs::read_to_string(path)?, format, mode)
+    }
+
+    /// Parses the points, empty lines and lines starting with `#` are ignored in a csv file, as
+    /// well as a header on its first line.
+    pub fn parse(content: &str, format: PointsFormat, mode: PointsMode) -> Result<Self> {
+        let points = match format {
+            PointsFormat::Csv => parse_csv(content)?,
+            PointsFormat::Json => parse_json(content)?,
+        };
+        let point_set = Self { points, mode };
+        if let Some(point) = point_set.points.iter().find(|point| !(point.x.is_finite() && point.y.is_finite())) {
+            return Err(Error::invalid_parameter("points", format!("invalid point ({}, {})", point.x, point.y)));
+        }
+        Ok(point_set)
+    }
+
+    /// Checks that every point is inside an image of `width` x `height` pixels.
+    pub fn validate(&self, width: u32, height: u32) -> Result<()> {
+        let (width, height) = (width as f64, height as f64);
+        let outside = self.points.iter()
+            .find(|point| !((0.0..=width).contains(&point.x) && (0.0..=height).contains(&point.y)));
+        match outside {
+            Some(point) => Err(Error::invalid_parameter(
+                "points",
+                format!("the point ({}, {}) is outside of the {}x{} image", point.x, point.y, width, height),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Combines the points with the points sampled on the edges, `sample` is only called when the
+    /// points are merged.
+    pub fn combine(&self, sample: impl FnOnce() -> Vec<Point>) -> Vec<Point> {
+        match self.mode {
+            PointsMode::Merge => {
+                let mut points = sample();
+                points.extend_from_slice(&self.points);
+                points
+            }
+            PointsMode::Replace => self.points.clone(),
+        }
+    }
+}
+
+fn parse_csv(content: &str) -> Result<Vec<Point>> {
+    let lines = content.lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+    let mut points = vec![];
+    for (position, (number, line)) in lines.enumerate() {
+        let mut values = line.split([',', ';', ' ', '\t'])
+            .filter(|value| !value.is_empty())
+            .map(|value| value.trim().parse::<f64>());
+        match (values.next(), values.next()) {
+            (Some(Ok(x)), Some(Ok(y))) => points.push(Point { x, y }),
+            // a header such as `x,y`
+            (Some(Err(_)), _) if position == 0 => {}
+            _ => return Err(Error::invalid_parameter("points", format!("invalid point `{}` on line {}", line, number))),
+        }
+    }
+    Ok(points)
+}
+
+fn parse_json(content: &str) -> Result<Vec<Point>> {
+    let points: JsonPoints = serde_json::from_str(content)
+        .map_err(|e| Error::invalid_parameter("points", format!("invalid json: {}", e)))?;
+    let (JsonPoints::List(points) | JsonPoints::Points { points } | JsonPoints::Vertices { vertices: points }) = points;
+    Ok(points.into_iter()
+        .map(|point| match point {
+            JsonPoint::Pair([x, y]) | JsonPoint::Object { x, y } => Point { x, y },
+        })
+        .collect())
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{PointSet, PointsFormat, PointsMode};
+    use crate::delaunay::Point;
+    use crate::error::test::assert_invalid;
+
+    fn points(coordinates: &[(f64, f64)]) -> Vec<Point> {
+        coordinates.iter().map(|(x, y)| Point { x: *x, y: *y }).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let csv = "x,y\n# eyes\n10,20\n30.5; 20\n\n 7 8 left corner\n";
+        let point_set = PointSet::parse(csv, PointsFormat::Csv, PointsMode::Merge).unwrap();
+        assert_eq!(point_set.points, points(&[(10.0, 20.0), (30.5, 20.0), (7.0, 8.0)]));
+
+        let expected = points(&[(1.0, 2.0), (3.0, 4.0)]);
+        for json in [
+            "[[1, 2], [3, 4]]",
+            r#"[{"x": 1, "y": 2}, {"x": 3, "y": 4}]"#,
+            r#"{"points": [[1, 2], {"x": 3, "y": 4}]}"#,
+            r#"{"width": 10, "height": 10, "vertices": [[1, 2], [3, 4]], "triangles": []}"#,
+        ] {
+            assert_eq!(PointSet::parse(json, PointsFormat::Json, PointsMode::Merge).unwrap().points, expected, "{}", json);
+        }
+
+        for (content, format) in [("1,2\nfoo,3\n", PointsFormat::Csv), ("1\n", PointsFormat::Csv), ("[[1]]", PointsFormat::Json)] {
+            assert_invalid(PointSet::parse(content, format, PointsMode::Merge), "points");
+        }
+        assert_eq!(PointsFormat::from_path("points.JSON"), PointsFormat::Json);
+        assert_eq!(PointsFormat::from_path("points.txt"), PointsFormat::Csv);
+    }
+
+    #[test]
+    fn test_combine() {
+        let sampled = points(&[(5.0, 5.0)]);
+        let mut point_set = PointSet { points: points(&[(1.0, 1.0)]), mode: PointsMode::Merge };
+        assert_eq!(point_set.combine(|| sampled.clone()), points(&[(5.0, 5.0), (1.0, 1.0)]));
+
+        point_set.mode = PointsMode::Replace;
+        assert_eq!(point_set.combine(|| panic!("the edges are not sampled")), points(&[(1.0, 1.0)]));
+
+        assert!(point_set.validate(1, 1).is_ok());
+        assert!(point_set.validate(0, 10).is_err());
+        assert_eq!("replace".parse::<PointsMode>(), Ok(PointsMode::Replace));
+    }
+}
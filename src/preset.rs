@@ -0,0 +1,134 @@
+use std::fmt;
+use std::str::FromStr;
+
+use resvg::usvg;
+
+use crate::config::Config;
+use crate::drawer::Drawer;
+use crate::Triangulation;
+
+
+/// Curated combinations of [`Triangulation`] and [`Drawer`] parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    LowPoly,
+    Wireframe,
+    Mosaic,
+    Sketch,
+    Dense,
+}
+
+impl Preset {
+
+    pub const ALL: [Preset; 5] = [Preset::LowPoly, Preset::Wireframe, Preset::Mosaic, Preset::Sketch, Preset::Dense];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::LowPoly => "low-poly",
+            Preset::Wireframe => "wireframe",
+            Preset::Mosaic => "mosaic",
+            Preset::Sketch => "sketch",
+            Preset::Dense => "dense",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Preset::LowPoly => "few large filled triangles without stroke",
+            Preset::Wireframe => "black edges only on a white background",
+            Preset::Mosaic => "many filled triangles separated by white joints",
+            Preset::Sketch => "thin grey edges on paper, like a pencil drawing",
+            Preset::Dense => "lots of small filled triangles, close to the source image",
+        }
+    }
+
+    /// Returns the parameters of the preset.
+    pub fn config(&self) -> Config {
+        match self {
+            Preset::LowPoly => Config {
+                triangulation: Triangulation { blur_factor: 2, max_points: 1000, point_rate: 0.05, ..Default::default() },
+                drawer: Drawer { only_wireframe: false, stroke_width: 0.0, ..Default::default() },
+            },
+            Preset::Wireframe => Config {
+                triangulation: Triangulation::default(),
+                drawer: Drawer {
+                    only_wireframe: true,
+                    stroke_width: 0.5,
+                    stroke_color: Some(usvg::Color::black()),
+                    with_background: true,
+                    background_color: Some(usvg::Color::white()),
+                    ..Default::default()
+                },
+            },
+            Preset::Mosaic => Config {
+                triangulation: Triangulation { max_points: 6000, point_rate: 0.2, ..Default::default() },
+                drawer: Drawer {
+                    only_wireframe: false,
+                    stroke_width: 1.0,
+                    stroke_color: Some(usvg::Color::white()),
+                    with_background: true,
+                    background_color: Some(usvg::Color::white()),
+                    ..Default::default()
+                },
+            },
+            Preset::Sketch => Config {
+                triangulation: Triangulation { grayscale: true, max_points: 4000, point_rate: 0.1, ..Default::default() },
+                drawer: Drawer {
+                    only_wireframe: true,
+                    stroke_width: 0.3,
+                    stroke_color: Some(usvg::Color::new_rgb(64, 64, 64)),
+                    with_background: true,
+                    background_color: Some(usvg::Color::new_rgb(250, 248, 240)),
+                    ..Default::default()
+                },
+            },
+            Preset::Dense => Config {
+                triangulation: Triangulation { sobel_factor: 4, max_points: 20000, point_rate: 0.3, ..Default::default() },
+                drawer: Drawer { only_wireframe: false, stroke_width: 0.0, ..Default::default() },
+            },
+        }
+    }
+}
+
+impl fmt::Display for Preset {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Preset::ALL.into_iter()
+            .find(|preset| preset.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Preset::ALL.iter().map(|preset| preset.name()).collect();
+                format!("unknown preset `{}` (available: {})", name, names.join(", "))
+            })
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::Preset;
+
+    #[test]
+    fn test_presets_are_valid() {
+        for preset in Preset::ALL {
+            let config = preset.config();
+            assert!(config.triangulation.validate().is_ok(), "invalid triangulation for {}", preset);
+            assert!(config.drawer.validate().is_ok(), "invalid drawer for {}", preset);
+        }
+    }
+
+    #[test]
+    fn test_from_str() {
+        for preset in Preset::ALL {
+            assert_eq!(preset.name().parse::<Preset>(), Ok(preset));
+        }
+        assert!("cubism".parse::<Preset>().is_err());
+    }
+}
@@ -0,0 +1,443 @@
+use resvg::tiny_skia;
+
+use crate::delaunay::Point;
+
+
+/// Scanline rasterizer filling and stroking triangles directly into a pixmap, used by
+/// [`RenderBackend::Raster`](crate::drawer::RenderBackend::Raster).
+///
+/// The antialiasing is analytic: the coverage of a pixel is derived from the distance between its
+/// center and the edges of the shape, so that each pixel is computed once whatever the quality.
+pub struct Rasterizer<'a> {
+    data: &'a mut [u8],
+    width: usize,
+    height: usize,
+}
+
+/// Half-plane `a * x + b * y + c >= 0`, normalized so that the left side is the distance to the edge.
+#[derive(Clone, Copy)]
+struct Edge {
+    a: f64,
+    b: f64,
+    c: f64,
+    /// rasterized without antialiasing, see [`Rasterizer::fill_triangle`]
+    sharp: bool,
+}
+
+impl Edge {
+
+    /// Edge from `from` to `to`, positive on the side of `inside`.
+    fn new(from: Point, to: Point, inside: Point, sharp: bool) -> Option<Self> {
+        // the coefficients are computed from the ends in a fixed order, so that the triangles on
+        // both sides of an edge get exactly opposite distances
+        let (from, to) = if (from.x, from.y) <= (to.x, to.y) { (from, to) } else { (to, from) };
+        let (dx, dy) = (to.x - from.x, to.y - from.y);
+        let length = dx.hypot(dy);
+        if length < f64::EPSILON {
+            return None;
+        }
+        let edge = Self { a: -dy / length, b: dx / length, c: (dy * from.x - dx * from.y) / length, sharp };
+        if edge.distance(inside) < 0.0 {
+            Some(Self { a: -edge.a, b: -edge.b, c: -edge.c, sharp })
+        } else {
+            Some(edge)
+        }
+    }
+
+    fn distance(&self, point: Point) -> f64 {
+        self.a * point.x + self.b * point.y + self.c
+    }
+
+    /// Part of the pixel centered on `point` covered by the half-plane.
+    fn coverage(&self, point: Point) -> f64 {
+        let distance = self.distance(point);
+        if !self.sharp {
+            return (distance + 0.5).clamp(0.0, 1.0);
+        }
+        // the pixels centered on the edge belong to the triangle on its top left side only
+        let owner = self.a > 0.0 || (self.a == 0.0 && self.b > 0.0);
+        if distance > 0.0 || (distance == 0.0 && owner) { 1.0 } else { 0.0 }
+    }
+
+    /// Restricts `span` to the `x` of the row `y` where the distance is at least `min`.
+    fn clip(&self, y: f64, min: f64, span: (f64, f64)) -> (f64, f64) {
+        let threshold = min - self.b * y - self.c;
+        if self.a.abs() < 1e-12 {
+            return if threshold <= 0.0 { span } else { (1.0, 0.0) };
+        }
+        let x = threshold / self.a;
+        if self.a > 0.0 { (span.0.max(x), span.1) } else { (span.0, span.1.min(x)) }
+    }
+}
+
+/// Rounded division by 255 of a value up to 255 * 255.
+fn div_255(value: u32) -> u8 {
+    let value = value + 128;
+    ((value + (value >> 8)) >> 8) as u8
+}
+
+/// Edge of a stroked triangle.
+struct Segment {
+    from: Point,
+    to: Point,
+    dx: f64,
+    dy: f64,
+    inverse_length_squared: f64,
+    radius: f64,
+    opacity: f64,
+}
+
+impl Segment {
+
+    fn new(from: Point, to: Point, radius: f64, opacity: f64) -> Self {
+        let (dx, dy) = (to.x - from.x, to.y - from.y);
+        let length_squared = dx * dx + dy * dy;
+        let inverse_length_squared = if length_squared > 0.0 { 1.0 / length_squared } else { 0.0 };
+        Self { from, to, dx, dy, inverse_length_squared, radius, opacity }
+    }
+
+    /// Alpha of the pixel centered on `point`, from its distance to the segment.
+    fn alpha(&self, point: Point) -> f64 {
+        let (x, y) = (point.x - self.from.x, point.y - self.from.y);
+        let t = ((x * self.dx + y * self.dy) * self.inverse_length_squared).clamp(0.0, 1.0);
+        let (x, y) = (x - t * self.dx, y - t * self.dy);
+        let distance = x * x + y * y;
+        if distance >= (self.radius + 0.5) * (self.radius + 0.5) {
+            0.0
+        } else if distance <= (self.radius - 0.5) * (self.radius - 0.5) {
+            self.opacity
+        } else {
+            (self.radius + 0.5 - distance.sqrt()).min(1.0) * self.opacity
+        }
+    }
+}
+
+/// Returns the `x` of the row `y` within `radius` of the segment from `from` to `to`.
+fn capsule_span(from: Point, to: Point, radius: f64, y: f64) -> Option<(f64, f64)> {
+    if y < from.y.min(to.y) - radius || y > from.y.max(to.y) + radius {
+        return None;
+    }
+    let mut span = (f64::INFINITY, f64::NEG_INFINITY);
+    let mut extend = |(min, max): (f64, f64)| {
+        if min <= max {
+            span = (span.0.min(min), span.1.max(max));
+        }
+    };
+
+    // round ends
+    for end in [from, to] {
+        let dy = y - end.y;
+        if dy.abs() <= radius {
+            let dx = (radius * radius - dy * dy).sqrt();
+            extend((end.x - dx, end.x + dx));
+        }
+    }
+
+    // band along the segment, between the two ends
+    let (dx, dy) = (to.x - from.x, to.y - from.y);
+    let length = dx.hypot(dy);
+    if length > f64::EPSILON {
+        let (ux, uy) = (dx / length, dy / length);
+        let mut band = (f64::NEG_INFINITY, f64::INFINITY);
+        // -radius <= normal . (p - from) <= radius and 0 <= direction . (p - from) <= length
+        for (a, b, min, max) in [(-uy, ux, -radius, radius), (ux, uy, 0.0, length)] {
+            let offset = b * (y - from.y) - a * from.x;
+            if a.abs() < 1e-12 {
+                if offset < min || offset > max {
+                    band = (1.0, 0.0);
+                }
+            } else {
+                let (x0, x1) = ((min - offset) / a, (max - offset) / a);
+                band = (band.0.max(x0.min(x1)), band.1.min(x0.max(x1)));
+            }
+        }
+        extend(band);
+    }
+
+    (span.0 <= span.1).then_some(span)
+}
+
+impl<'a> Rasterizer<'a> {
+
+    pub fn new(pixmap: &'a mut tiny_skia::Pixmap) -> Self {
+        let (width, height) = (pixmap.width() as usize, pixmap.height() as usize);
+        Self { data: pixmap.data_mut(), width, height }
+    }
+
+    /// Range of the rows between `min_y` and `max_y`.
+    fn rows(&self, min_y: f64, max_y: f64) -> std::ops::Range<usize> {
+        let first = (min_y - 0.5).floor().max(0.0) as usize;
+        let last = ((max_y + 0.5).ceil().max(0.0) as usize).min(self.height);
+        first..last.max(first)
+    }
+
+    /// Range of the columns whose center is in `span`.
+    fn columns(&self, (min_x, max_x): (f64, f64)) -> std::ops::Range<usize> {
+        if min_x.is_nan() || max_x.is_nan() || min_x > max_x {
+            return 0..0;
+        }
+        // `as usize` truncates, which is a floor for the positive values and faster than `f64::floor`
+        let (min_x, max_x) = (min_x - 0.5, max_x - 0.5);
+        let first = if min_x <= 0.0 { 0 } else { min_x as usize + ((min_x as usize as f64) < min_x) as usize };
+        let last = if max_x < 0.0 { 0 } else { (max_x as usize).saturating_add(1).min(self.width) };
+        first..last.max(first)
+    }
+
+    /// Blends `color` (straight alpha) over the premultiplied pixels of the row `y` in `columns`.
+    fn blend(&mut self, y: usize, columns: std::ops::Range<usize>, color: [u8; 3], alpha: f64) {
+        let alpha = (alpha.clamp(0.0, 1.0) * 255.0 + 0.5) as u32;
+        if alpha == 0 || columns.is_empty() {
+            return;
+        }
+        let row = y * self.width;
+        let pixels = &mut self.data[(row + columns.start) * 4..(row + columns.end) * 4];
+        if alpha == 255 {
+            let color = [color[0], color[1], color[2], 255];
+            pixels.chunks_exact_mut(4).for_each(|pixel| pixel.copy_from_slice(&color));
+            return;
+        }
+
+        let source = [color[0] as u32 * alpha, color[1] as u32 * alpha, color[2] as u32 * alpha, 255 * alpha];
+        let inverse = 255 - alpha;
+        for pixel in pixels.chunks_exact_mut(4) {
+            for channel in 0..4 {
+                pixel[channel] = div_255(source[channel] + pixel[channel] as u32 * inverse);
+            }
+        }
+    }
+
+    /// Fills the triangle with `color` at `opacity`.
+    ///
+    /// The edges flagged in `sharp` (starting at each vertex) are not antialiased: a pixel is either
+    /// inside or outside, so that two triangles sharing such an edge cover every pixel along it
+    /// exactly once instead of letting the background show through their partial coverages.
+    pub fn fill_triangle(&mut self, vertex: [Point; 3], sharp: [bool; 3], color: [u8; 3], opacity: f64) {
+        let [a, b, c] = vertex;
+        let (Some(ab), Some(bc), Some(ca)) = (
+            Edge::new(a, b, c, sharp[0]),
+            Edge::new(b, c, a, sharp[1]),
+            Edge::new(c, a, b, sharp[2]),
+        ) else {
+            return;
+        };
+        let edges = [ab, bc, ca];
+        let min_y = a.y.min(b.y).min(c.y);
+        let max_y = a.y.max(b.y).max(c.y);
+        let unbounded = (f64::NEG_INFINITY, f64::INFINITY);
+
+        for y in self.rows(min_y, max_y) {
+            let center_y = y as f64 + 0.5;
+            let outer = self.columns(edges.iter().fold(unbounded, |span, edge| edge.clip(center_y, -0.5, span)));
+            if outer.is_empty() {
+                continue;
+            }
+            // the pixels at least half a pixel inside every edge are fully covered
+            let inner = self.columns(edges.iter().fold(unbounded, |span, edge| edge.clip(center_y, 0.5, span)));
+            let inner = if inner.is_empty() { outer.end..outer.end } else { inner.start.max(outer.start)..inner.end.min(outer.end) };
+
+            for x in (outer.start..inner.start).chain(inner.end..outer.end) {
+                let center = Point { x: x as f64 + 0.5, y: center_y };
+                let coverage = edges.iter().map(|edge| edge.coverage(center)).fold(1.0, f64::min);
+                self.blend(y, x..x + 1, color, coverage * opacity);
+            }
+            self.blend(y, inner, color, opacity);
+        }
+    }
+
+    /// Strokes the outline of the triangle with round joins, `widths` being the width of the edges
+    /// starting at each vertex.
+    pub fn stroke_triangle(&mut self, vertex: [Point; 3], widths: [f64; 3], color: [u8; 3], opacity: f64) {
+        // lines thinner than a pixel are drawn one pixel wide with a lower opacity
+        let segments: Vec<Segment> = (0..3)
+            .filter(|&i| widths[i] > 0.0)
+            .map(|i| Segment::new(vertex[i], vertex[(i + 1) % 3], (widths[i] / 2.0).max(0.5), opacity * widths[i].min(1.0)))
+            .collect();
+        let Some(max_radius) = segments.iter().map(|segment| segment.radius).reduce(f64::max) else {
+            return;
+        };
+        let min_y = vertex.iter().map(|point| point.y).fold(f64::INFINITY, f64::min);
+        let max_y = vertex.iter().map(|point| point.y).fold(f64::NEG_INFINITY, f64::max);
+
+        let mut columns = Vec::with_capacity(3);
+        let mut merged = Vec::with_capacity(3);
+        for y in self.rows(min_y - max_radius, max_y + max_radius) {
+            let center_y = y as f64 + 0.5;
+
+            // the columns near each segment, merged so that each pixel is blended once
+            columns.clear();
+            columns.extend(segments.iter().map(|segment| {
+                capsule_span(segment.from, segment.to, segment.radius + 0.5, center_y)
+                    .map_or(0..0, |span| self.columns(span))
+            }));
+            merged.clear();
+            merged.extend(columns.iter().filter(|range| !range.is_empty()).cloned());
+            merged.sort_by_key(|range: &std::ops::Range<usize>| range.start);
+            merged.dedup_by(|next, last| {
+                let overlap = next.start <= last.end;
+                if overlap {
+                    last.end = last.end.max(next.end);
+                }
+                overlap
+            });
+
+            for range in &merged {
+                for x in range.clone() {
+                    let center = Point { x: x as f64 + 0.5, y: center_y };
+                    let alpha = segments.iter().zip(&columns)
+                        .filter(|(_, columns)| columns.contains(&x))
+                        .map(|(segment, _)| segment.alpha(center))
+                        .fold(0.0, f64::max);
+                    self.blend(y, x..x + 1, color, alpha);
+                }
+            }
+        }
+    }
+
+    /// Fills the ellipse centered on `center` with the horizontal and vertical `radii`, with
+    /// `color` at `opacity`.
+    pub fn fill_ellipse(&mut self, center: Point, radii: (f64, f64), color: [u8; 3], opacity: f64) {
+        let (radius_x, radius_y) = radii;
+        if !(radius_x > 0.0 && radius_y > 0.0) {
+            return;
+        }
+        // the pixels whose center is less than half a pixel outside
+        let (outer_x, outer_y) = (radius_x + 0.5, radius_y + 0.5);
+
+        for y in self.rows(center.y - radius_y, center.y + radius_y) {
+            let dy = y as f64 + 0.5 - center.y;
+            let height = 1.0 - (dy / outer_y).powi(2);
+            if height <= 0.0 {
+                continue;
+            }
+            let half_width = outer_x * height.sqrt();
+            for x in self.columns((center.x - half_width, center.x + half_width)) {
+                // distance to the outline estimated from the implicit equation and its gradient
+                let (u, v) = ((x as f64 + 0.5 - center.x) / radius_x, dy / radius_y);
+                let gradient = 2.0 * (u / radius_x).hypot(v / radius_y);
+                let distance = if gradient > 0.0 { (u * u + v * v - 1.0) / gradient } else { -radius_x.min(radius_y) };
+                self.blend(y, x..x + 1, color, (0.5 - distance).clamp(0.0, 1.0) * opacity);
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use resvg::tiny_skia;
+
+    use super::{capsule_span, Rasterizer};
+    use crate::delaunay::Point;
+
+    fn alpha(pixmap: &tiny_skia::Pixmap, x: u32, y: u32) -> u8 {
+        pixmap.pixel(x, y).unwrap().alpha()
+    }
+
+    #[test]
+    fn test_fill_triangle() {
+        let mut pixmap = tiny_skia::Pixmap::new(10, 10).unwrap();
+        let triangle = [Point { x: 0.0, y: 0.0 }, Point { x: 10.0, y: 0.0 }, Point { x: 0.0, y: 10.0 }];
+        Rasterizer::new(&mut pixmap).fill_triangle(triangle, [false; 3], [255, 0, 0], 1.0);
+
+        assert_eq!(pixmap.pixel(1, 1).unwrap(), tiny_skia::PremultipliedColorU8::from_rgba(255, 0, 0, 255).unwrap());
+        assert_eq!(alpha(&pixmap, 9, 9), 0);
+        // the pixels crossed by the diagonal are half covered
+        assert!((100..=160).contains(&alpha(&pixmap, 4, 5)));
+
+        // the vertex order does not matter, flat triangles are skipped
+        let mut reversed = tiny_skia::Pixmap::new(10, 10).unwrap();
+        let mut rasterizer = Rasterizer::new(&mut reversed);
+        rasterizer.fill_triangle([triangle[2], triangle[1], triangle[0]], [false; 3], [255, 0, 0], 1.0);
+        rasterizer.fill_triangle([triangle[0], triangle[0], triangle[1]], [false; 3], [0, 255, 0], 1.0);
+        assert_eq!(pixmap, reversed);
+    }
+
+    #[test]
+    fn test_fill_sharp_edges() {
+        // two triangles sharing a diagonal that crosses pixel centers
+        let [a, b, c, d] = [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)].map(|(x, y)| Point { x, y });
+        let mut antialiased = tiny_skia::Pixmap::new(10, 10).unwrap();
+        let mut rasterizer = Rasterizer::new(&mut antialiased);
+        rasterizer.fill_triangle([a, b, c], [false; 3], [255, 0, 0], 1.0);
+        rasterizer.fill_triangle([a, c, d], [false; 3], [255, 0, 0], 1.0);
+        assert!(alpha(&antialiased, 4, 4) < 255);
+
+        let mut seamless = tiny_skia::Pixmap::new(10, 10).unwrap();
+        let mut rasterizer = Rasterizer::new(&mut seamless);
+        rasterizer.fill_triangle([a, b, c], [false, false, true], [255, 0, 0], 1.0);
+        rasterizer.fill_triangle([c, d, a], [false, false, true], [255, 0, 0], 0.5);
+        for (x, y) in (0..10).flat_map(|x| (0..10).map(move |y| (x, y))) {
+            // every pixel is covered once, by one of the two triangles
+            let expected = if x >= y { 255 } else { 128 };
+            assert_eq!(alpha(&seamless, x, y), expected, "pixel {}x{}", x, y);
+        }
+    }
+
+    #[test]
+    fn test_blend() {
+        let mut pixmap = tiny_skia::Pixmap::new(4, 4).unwrap();
+        let square = [Point { x: -1.0, y: -1.0 }, Point { x: 9.0, y: -1.0 }, Point { x: -1.0, y: 9.0 }];
+        let mut rasterizer = Rasterizer::new(&mut pixmap);
+        rasterizer.fill_triangle(square, [false; 3], [0, 0, 255], 1.0);
+        rasterizer.fill_triangle(square, [false; 3], [255, 0, 0], 0.5);
+
+        // premultiplied result of a half transparent red over an opaque blue
+        let pixel = pixmap.pixel(0, 0).unwrap();
+        assert_eq!((pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()), (128, 0, 127, 255));
+    }
+
+    #[test]
+    fn test_stroke_triangle() {
+        let mut pixmap = tiny_skia::Pixmap::new(20, 20).unwrap();
+        let triangle = [Point { x: 2.0, y: 2.0 }, Point { x: 18.0, y: 2.0 }, Point { x: 2.0, y: 18.0 }];
+        Rasterizer::new(&mut pixmap).stroke_triangle(triangle, [2.0; 3], [0, 0, 0], 1.0);
+
+        assert_eq!(alpha(&pixmap, 10, 1), 255);
+        assert_eq!(alpha(&pixmap, 10, 2), 255);
+        assert_eq!(alpha(&pixmap, 1, 10), 255);
+        assert_eq!(alpha(&pixmap, 6, 6), 0);
+        assert_eq!(alpha(&pixmap, 10, 5), 0);
+
+        // hairlines are drawn with a lower opacity, here over the two rows around y = 2
+        let mut hairline = tiny_skia::Pixmap::new(20, 20).unwrap();
+        Rasterizer::new(&mut hairline).stroke_triangle(triangle, [0.2; 3], [0, 0, 0], 1.0);
+        assert_eq!(alpha(&hairline, 10, 1), alpha(&hairline, 10, 2));
+        assert!((45..=57).contains(&(alpha(&hairline, 10, 1) as u32 + alpha(&hairline, 10, 2) as u32)));
+
+        // edges without width are not drawn
+        let mut partial = tiny_skia::Pixmap::new(20, 20).unwrap();
+        Rasterizer::new(&mut partial).stroke_triangle(triangle, [2.0, 0.0, 0.0], [0, 0, 0], 1.0);
+        assert_eq!(alpha(&partial, 10, 2), 255);
+        assert_eq!(alpha(&partial, 1, 10), 0);
+        assert_eq!(alpha(&partial, 10, 10), 0);
+    }
+
+    #[test]
+    fn test_fill_ellipse() {
+        let mut pixmap = tiny_skia::Pixmap::new(20, 20).unwrap();
+        Rasterizer::new(&mut pixmap).fill_ellipse(Point { x: 10.0, y: 10.0 }, (4.0, 4.0), [0, 0, 255], 1.0);
+        assert_eq!(alpha(&pixmap, 10, 10), 255);
+        assert_eq!(alpha(&pixmap, 7, 10), 255);
+        assert_eq!(alpha(&pixmap, 15, 10), 0);
+        assert_eq!(alpha(&pixmap, 13, 13), 0);
+        // the area of the disc
+        let covered: u32 = (0..20).flat_map(|x| (0..20).map(move |y| (x, y))).map(|(x, y)| alpha(&pixmap, x, y) as u32).sum();
+        assert!((covered as f64 / 255.0 - 16.0 * std::f64::consts::PI).abs() < 1.0);
+
+        let mut ellipse = tiny_skia::Pixmap::new(20, 20).unwrap();
+        Rasterizer::new(&mut ellipse).fill_ellipse(Point { x: 10.0, y: 10.0 }, (8.0, 2.0), [0, 0, 255], 1.0);
+        assert_eq!(alpha(&ellipse, 3, 10), 255);
+        assert_eq!(alpha(&ellipse, 10, 13), 0);
+    }
+
+    #[test]
+    fn test_capsule_span() {
+        let (from, to) = (Point { x: 0.0, y: 0.0 }, Point { x: 10.0, y: 0.0 });
+        assert_eq!(capsule_span(from, to, 1.0, 0.0), Some((-1.0, 11.0)));
+        assert_eq!(capsule_span(from, to, 1.0, 2.0), None);
+
+        let (from, to) = (Point { x: 0.0, y: 0.0 }, Point { x: 10.0, y: 10.0 });
+        let (min, max) = capsule_span(from, to, 1.0, 5.0).unwrap();
+        assert!((min - (5.0 - 2f64.sqrt())).abs() < 1e-9 && (max - (5.0 + 2f64.sqrt())).abs() < 1e-9);
+    }
+}
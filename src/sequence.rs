@@ -0,0 +1,161 @@
+use std::collections::HashSet;
+
+use image::RgbaImage;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::delaunay::Point;
+use crate::error::{Error, Result};
+use crate::{Edges, Triangulation};
+
+
+/// Default largest change of the edge strength under a point for it to be kept in the next frame.
+pub const DEFAULT_TOLERANCE: u8 = 16;
+
+/// Edges and points of the previous frame, the points in the pixels of the source image.
+#[derive(Debug, Clone)]
+struct Frame {
+    edges: Edges,
+    points: Vec<Point>,
+}
+
+/// Samples the points of the consecutive frames of a video.
+///
+/// Sampling each frame on its own picks new random points every time and the triangulation
+/// flickers. The points of the previous frame are kept where the edges did not change, only the
+/// points of the moving parts are picked again, with a seeded random generator.
+#[derive(Debug, Clone)]
+pub struct Sequence {
+    triangulation: Triangulation,
+    /// largest change of the edge strength under a point for it to be kept in the next frame
+    tolerance: u8,
+    rng: StdRng,
+    previous: Option<Frame>,
+    kept: usize,
+}
+
+impl Sequence {
+
+    /// Starts a sequence, the random generator is seeded with the seed of the triangulation
+    /// (or a random seed when it has none).
+    pub fn new(triangulation: Triangulation, tolerance: u8) -> Result<Self> {
+        triangulation.validate()?;
+        let rng = match triangulation.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        Ok(Self { triangulation, tolerance, rng, previous: None, kept: 0 })
+    }
+
+    /// Number of points of the last frame kept from the frame before.
+    pub fn kept(&self) -> usize {
+        self.kept
+    }
+
+    /// Samples the points of the next frame, see [`Triangulation::sample_points`].
+    pub fn sample_points(&mut self, source_image: &RgbaImage) -> Result<Vec<Point>> {
+        let edges = self.triangulation.detect_edges(source_image)?;
+        self.sample_edges(&edges)
+    }
+
+    /// Samples the points of the next frame on edges already detected, see
+    /// [`Triangulation::detect_edges`].
+    pub fn sample_edges(&mut self, edges: &Edges) -> Result<Vec<Point>> {
+        self.triangulation.validate()?;
+        let edge_points = self.triangulation.edge_points(&edges.image);
+        let limit = self.triangulation.point_limit(edges.covered(edge_points.len()));
+
+        // a point stays while it is still on an edge of about the same strength
+        let mut points = match self.previous.take() {
+            Some(previous) if previous.edges.image.dimensions() == edges.image.dimensions()
+                && previous.edges.source_size == edges.source_size => {
+                let on_edge: HashSet<(u32, u32)> = edge_points.iter()
+                    .map(|point| (point.x as u32, point.y as u32))
+                    .collect();
+                previous.points.into_iter()
+                    .filter(|point| match edges.edge_pixel(*point) {
+                        Some((x, y)) => {
+                            let before = previous.edges.image.get_pixel(x, y)[0];
+                            let after = edges.image.get_pixel(x, y)[0];
+                            on_edge.contains(&(x, y)) && before.abs_diff(after) <= self.tolerance
+                        }
+                        None => false,
+                    })
+                    .collect()
+            }
+            _ => vec![],
+        };
+        while points.len() > limit {
+            points.swap_remove(self.rng.gen_range(0..points.len()));
+        }
+        self.kept = points.len();
+        points.extend(edges.pick_points(&edge_points, limit - points.len(), &mut self.rng));
+
+        if points.is_empty() {
+            return Err(Error::NoPoints);
+        }
+        self.previous = Some(Frame { edges: edges.clone(), points: points.clone() });
+        Ok(points)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use image::{Rgba, RgbaImage};
+
+    use super::{Sequence, DEFAULT_TOLERANCE};
+    use crate::error::Error;
+    use crate::Triangulation;
+
+    /// A white square on a black background, with its left side at `left`.
+    fn frame(left: u32) -> RgbaImage {
+        RgbaImage::from_fn(80, 60, |x, y| {
+            if (left..left + 30).contains(&x) && (15..45).contains(&y) {
+                Rgba::from([255, 255, 255, 255])
+            } else {
+                Rgba::from([0, 0, 0, 255])
+            }
+        })
+    }
+
+    #[test]
+    fn test_sample_points() {
+        let triangulation = Triangulation { seed: Some(7), point_rate: 0.2, ..Default::default() };
+        let mut sequence = Sequence::new(triangulation.clone(), DEFAULT_TOLERANCE).unwrap();
+
+        let first = sequence.sample_points(&frame(10)).unwrap();
+        assert_eq!(sequence.kept(), 0);
+        assert_eq!(first, triangulation.sample_points(&frame(10)).unwrap());
+
+        // a still frame keeps every point
+        let second = sequence.sample_points(&frame(10)).unwrap();
+        assert_eq!(second, first);
+        assert_eq!(sequence.kept(), first.len());
+
+        // the points of the moving edges are picked again, the others stay
+        let third = sequence.sample_points(&frame(20)).unwrap();
+        assert!(sequence.kept() > 0 && sequence.kept() < second.len());
+        assert!(third.iter().take(sequence.kept()).all(|point| second.contains(point)));
+
+        // the same seed gives the same sequence
+        let mut replay = Sequence::new(triangulation, DEFAULT_TOLERANCE).unwrap();
+        let replayed: Vec<_> = [10, 10, 20].iter().map(|left| replay.sample_points(&frame(*left)).unwrap()).collect();
+        assert_eq!(replayed, vec![first, second, third]);
+    }
+
+    #[test]
+    fn test_sample_points_errors() {
+        let mut sequence = Sequence::new(Triangulation::default(), DEFAULT_TOLERANCE).unwrap();
+        let blank = RgbaImage::from_pixel(20, 20, Rgba::from([128, 128, 128, 255]));
+        assert!(matches!(sequence.sample_points(&blank), Err(Error::NoPoints)));
+
+        // a frame of another size starts over
+        sequence.sample_points(&frame(10)).unwrap();
+        sequence.sample_points(&RgbaImage::from_fn(40, 40, |x, _| Rgba::from([if x < 20 { 0 } else { 255 }, 0, 0, 255]))).unwrap();
+        assert_eq!(sequence.kept(), 0);
+
+        let invalid = Triangulation { max_points: 0, ..Default::default() };
+        assert!(matches!(Sequence::new(invalid, DEFAULT_TOLERANCE), Err(Error::InvalidParameter { .. })));
+    }
+}
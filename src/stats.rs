@@ -0,0 +1,344 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use image::{Rgba, RgbaImage};
+use serde::Serialize;
+
+use crate::delaunay::Triangle;
+use crate::drawer::{Drawing, Fit};
+use crate::error::{Error, Result};
+
+
+/// Upper bounds, in degrees, of the bins of [`MeshStats::min_angle_histogram`].
+pub const ANGLE_BINS: [f64; 6] = [10.0, 20.0, 30.0, 40.0, 50.0, 60.0];
+
+/// Upper bounds of the bins of [`MeshStats::aspect_ratio_histogram`], the last bin holds the
+/// larger ratios.
+pub const ASPECT_RATIO_BINS: [f64; 6] = [1.5, 2.0, 3.0, 5.0, 10.0, f64::INFINITY];
+
+/// Smallest, mean, median and largest of a list of values, all 0 for an empty list.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize)]
+pub struct Distribution {
+    pub min: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub max: f64,
+}
+
+impl Distribution {
+
+    pub fn new(mut values: Vec<f64>) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        values.sort_by(f64::total_cmp);
+        let count = values.len();
+        Self {
+            min: values[0],
+            mean: values.iter().sum::<f64>() / count as f64,
+            // the middle value, or the mean of the two middle values
+            median: (values[(count - 1) / 2] + values[count / 2]) / 2.0,
+            max: values[count - 1],
+        }
+    }
+}
+
+impl fmt::Display for Distribution {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "min {:.2}, mean {:.2}, median {:.2}, max {:.2}", self.min, self.mean, self.median, self.max)
+    }
+}
+
+/// Shape of the triangles of a mesh.
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct MeshStats {
+    /// number of distinct vertices
+    pub points: usize,
+    pub triangles: usize,
+    /// triangles without area, left out of the angles and aspect ratios
+    pub degenerate: usize,
+    /// smallest angle of each triangle, in degrees (60 for an equilateral triangle)
+    pub min_angle: Distribution,
+    /// number of triangles whose smallest angle is below each of [`ANGLE_BINS`]
+    pub min_angle_histogram: [usize; 6],
+    /// circumradius over twice the inradius of each triangle (1 for an equilateral triangle)
+    pub aspect_ratio: Distribution,
+    /// number of triangles whose aspect ratio is below each of [`ASPECT_RATIO_BINS`]
+    pub aspect_ratio_histogram: [usize; 6],
+    /// area of each triangle, in square pixels
+    pub area: Distribution,
+}
+
+impl MeshStats {
+
+    pub fn new(triangles: &[Triangle]) -> Self {
+        let points = triangles.iter()
+            .flat_map(|triangle| triangle.vertex())
+            .map(|point| (point.x.to_bits(), point.y.to_bits()))
+            .collect::<HashSet<_>>()
+            .len();
+
+        let mut stats = Self { points, triangles: triangles.len(), ..Default::default() };
+        let (mut min_angles, mut aspect_ratios) = (vec![], vec![]);
+        for triangle in triangles {
+            let [p0, p1, p2] = triangle.vertex();
+            let sides = [(p1, p2), (p2, p0), (p0, p1)].map(|(a, b)| (a.x - b.x).hypot(a.y - b.y));
+            let area = triangle.area();
+            if area <= f64::EPSILON || sides.iter().any(|side| *side <= f64::EPSILON) {
+                stats.degenerate += 1;
+                continue;
+            }
+
+            // the smallest angle is opposite the shortest side
+            let [a, b, c] = sides;
+            let shortest = a.min(b).min(c);
+            let min_angle = (2.0 * area / (a * b * c / shortest)).clamp(-1.0, 1.0).asin().to_degrees();
+            let perimeter = a + b + c;
+            let aspect_ratio = a * b * c * perimeter / (16.0 * area * area);
+
+            min_angles.push(min_angle);
+            aspect_ratios.push(aspect_ratio);
+        }
+
+        for angle in &min_angles {
+            let bin = ANGLE_BINS.iter().position(|bound| angle < bound).unwrap_or(ANGLE_BINS.len() - 1);
+            stats.min_angle_histogram[bin] += 1;
+        }
+        for ratio in &aspect_ratios {
+            let bin = ASPECT_RATIO_BINS.iter().position(|bound| ratio < bound).unwrap_or(ASPECT_RATIO_BINS.len() - 1);
+            stats.aspect_ratio_histogram[bin] += 1;
+        }
+        stats.min_angle = Distribution::new(min_angles);
+        stats.aspect_ratio = Distribution::new(aspect_ratios);
+        stats.area = Distribution::new(triangles.iter().map(Triangle::area).collect());
+        stats
+    }
+}
+
+/// Difference between the source image and the rendered image, both blended over white.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ImageError {
+    /// mean squared error of the red, green and blue channels, between 0 and 255²
+    pub mse: f64,
+    /// peak signal-to-noise ratio in decibels, `None` when the images are identical
+    pub psnr: Option<f64>,
+    /// structural similarity of the luminance, between -1 and 1 (identical images)
+    pub ssim: f64,
+}
+
+/// Side of the windows of the structural similarity, a window every half side.
+const SSIM_WINDOW: u32 = 8;
+
+fn over_white(pixel: &Rgba<u8>) -> [f64; 3] {
+    let alpha = pixel[3] as f64 / 255.0;
+    [0, 1, 2].map(|channel| pixel[channel] as f64 * alpha + 255.0 * (1.0 - alpha))
+}
+
+fn luminance([r, g, b]: [f64; 3]) -> f64 {
+    0.299 * r + 0.587 * g + 0.114 * b
+}
+
+impl ImageError {
+
+    /// Compares two images of the same size.
+    pub fn new(source_image: &RgbaImage, rendered_image: &RgbaImage) -> Result<Self> {
+        if source_image.dimensions() != rendered_image.dimensions() {
+            return Err(Error::invalid_parameter(
+                "rendered_image",
+                format!(
+                    "its size {:?} differs from the size of the source image {:?}",
+                    rendered_image.dimensions(), source_image.dimensions(),
+                ),
+            ));
+        }
+        let (width, height) = source_image.dimensions();
+        if width == 0 || height == 0 {
+            return Err(Error::DegenerateGeometry(format!("the source image is empty ({}x{})", width, height)));
+        }
+
+        let source: Vec<[f64; 3]> = source_image.pixels().map(over_white).collect();
+        let rendered: Vec<[f64; 3]> = rendered_image.pixels().map(over_white).collect();
+
+        let squared_error: f64 = source.iter().zip(&rendered)
+            .flat_map(|(a, b)| (0..3).map(move |channel| (a[channel] - b[channel]).powi(2)))
+            .sum();
+        let mse = squared_error / (source.len() * 3) as f64;
+        let psnr = (mse > 0.0).then(|| 10.0 * (255.0 * 255.0 / mse).log10());
+
+        let source: Vec<f64> = source.into_iter().map(luminance).collect();
+        let rendered: Vec<f64> = rendered.into_iter().map(luminance).collect();
+        Ok(Self { mse, psnr, ssim: ssim(&source, &rendered, width, height) })
+    }
+
+    /// Renders `drawing` at its original size and compares it to the source image.
+    pub fn of_drawing(source_image: &RgbaImage, drawing: &Drawing) -> Result<Self> {
+        Self::new(source_image, &drawing.to_image_with(Fit::Original)?)
+    }
+}
+
+/// Mean structural similarity of the windows of two luminance images.
+fn ssim(source: &[f64], rendered: &[f64], width: u32, height: u32) -> f64 {
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+    // the windows cover the whole image, even when it is smaller than a window
+    let starts = |size: u32| {
+        let window = SSIM_WINDOW.min(size);
+        let mut starts: Vec<u32> = (0..=size - window).step_by((SSIM_WINDOW / 2) as usize).collect();
+        if starts.last() != Some(&(size - window)) {
+            starts.push(size - window);
+        }
+        (starts, window)
+    };
+    let (columns, window_width) = starts(width);
+    let (rows, window_height) = starts(height);
+
+    let mut total = 0.0;
+    for y in &rows {
+        for x in &columns {
+            let indices = (*y..y + window_height)
+                .flat_map(|y| (*x..x + window_width).map(move |x| (y * width + x) as usize));
+            let count = (window_width * window_height) as f64;
+            let (mut sum_a, mut sum_b, mut sum_aa, mut sum_bb, mut sum_ab) = (0.0, 0.0, 0.0, 0.0, 0.0);
+            for index in indices {
+                let (a, b) = (source[index], rendered[index]);
+                sum_a += a;
+                sum_b += b;
+                sum_aa += a * a;
+                sum_bb += b * b;
+                sum_ab += a * b;
+            }
+            let (mean_a, mean_b) = (sum_a / count, sum_b / count);
+            let variance_a = sum_aa / count - mean_a * mean_a;
+            let variance_b = sum_bb / count - mean_b * mean_b;
+            let covariance = sum_ab / count - mean_a * mean_b;
+            total += ((2.0 * mean_a * mean_b + C1) * (2.0 * covariance + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (variance_a + variance_b + C2));
+        }
+    }
+    total / (rows.len() * columns.len()) as f64
+}
+
+/// Quality report of a triangulated image, to compare parameter sets.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Stats {
+    pub mesh: MeshStats,
+    /// `None` when the rendered image was not compared to the source image
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ImageError>,
+}
+
+impl Stats {
+
+    /// Measures the triangles and compares their rendered image to the source image, both of the
+    /// same size.
+    pub fn new(source_image: &RgbaImage, triangles: &[Triangle], rendered_image: &RgbaImage) -> Result<Self> {
+        Ok(Self {
+            mesh: MeshStats::new(triangles),
+            error: Some(ImageError::new(source_image, rendered_image)?),
+        })
+    }
+
+    /// Measures the triangles and compares their drawing, rendered at its original size, to the
+    /// source image.
+    pub fn of_drawing(source_image: &RgbaImage, triangles: &[Triangle], drawing: &Drawing) -> Result<Self> {
+        Self::new(source_image, triangles, &drawing.to_image_with(Fit::Original)?)
+    }
+}
+
+impl fmt::Display for Stats {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mesh = &self.mesh;
+        writeln!(f, "points          {}", mesh.points)?;
+        writeln!(f, "triangles       {} ({} degenerate)", mesh.triangles, mesh.degenerate)?;
+        writeln!(f, "min angle       {}", mesh.min_angle)?;
+        let mut lower = 0.0;
+        for (bound, count) in ANGLE_BINS.iter().zip(mesh.min_angle_histogram) {
+            writeln!(f, "  {:<13} {}", format!("{}-{}", lower, bound), count)?;
+            lower = *bound;
+        }
+        writeln!(f, "aspect ratio    {}", mesh.aspect_ratio)?;
+        let mut lower = 1.0;
+        for (bound, count) in ASPECT_RATIO_BINS.iter().zip(mesh.aspect_ratio_histogram) {
+            let range = if bound.is_finite() { format!("{}-{}", lower, bound) } else { format!("{}+", lower) };
+            writeln!(f, "  {:<13} {}", range, count)?;
+            lower = *bound;
+        }
+        write!(f, "area            {}", mesh.area)?;
+        if let Some(error) = &self.error {
+            let psnr = error.psnr.map(|psnr| format!("{:.2} dB", psnr)).unwrap_or_else(|| "inf".to_owned());
+            write!(f, "\nmse             {:.2}\npsnr            {}\nssim            {:.4}", error.mse, psnr, error.ssim)?;
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use image::{Rgba, RgbaImage};
+
+    use super::{Distribution, ImageError, MeshStats, Stats};
+    use crate::delaunay::{Point, Triangle};
+    use crate::drawer::{Drawable, Drawer, Fit};
+    use crate::error::Error;
+
+    #[test]
+    fn test_mesh_stats() {
+        let (a, b, c, d) = (Point { x: 0.0, y: 0.0 }, Point { x: 10.0, y: 0.0 }, Point { x: 0.0, y: 10.0 }, Point { x: 10.0, y: 10.0 });
+        let equilateral = Triangle::new(a, b, Point { x: 5.0, y: 75f64.sqrt() });
+        let flat = Triangle::new(a, b, Point { x: 20.0, y: 0.0 });
+        let stats = MeshStats::new(&[Triangle::new(a, b, c), Triangle::new(b, c, d), equilateral, flat]);
+
+        assert_eq!((stats.points, stats.triangles, stats.degenerate), (6, 4, 1));
+        assert!((stats.min_angle.min - 45.0).abs() < 1e-9);
+        assert!((stats.min_angle.max - 60.0).abs() < 1e-9);
+        assert_eq!(stats.min_angle_histogram, [0, 0, 0, 0, 2, 1]);
+        assert!((stats.aspect_ratio.min - 1.0).abs() < 1e-9);
+        assert!((stats.aspect_ratio.max - (1.0 + 2f64.sqrt()) / 2.0).abs() < 1e-9);
+        assert_eq!(stats.aspect_ratio_histogram, [3, 0, 0, 0, 0, 0]);
+        assert_eq!(stats.area.min, 0.0);
+        assert_eq!(stats.area.max, 50.0);
+
+        assert_eq!(MeshStats::new(&[]).min_angle, Distribution::default());
+        assert_eq!(Distribution::new(vec![4.0, 1.0, 3.0, 2.0]), Distribution { min: 1.0, mean: 2.5, median: 2.5, max: 4.0 });
+    }
+
+    #[test]
+    fn test_image_error() {
+        let gradient = RgbaImage::from_fn(20, 12, |x, y| Rgba::from([(x * 12) as u8, (y * 20) as u8, 100, 255]));
+        let same = ImageError::new(&gradient, &gradient).unwrap();
+        assert_eq!((same.mse, same.psnr), (0.0, None));
+        assert!((same.ssim - 1.0).abs() < 1e-9);
+
+        let flat = RgbaImage::from_pixel(20, 12, Rgba::from([120, 110, 100, 255]));
+        let error = ImageError::new(&gradient, &flat).unwrap();
+        assert!(error.mse > 0.0 && error.psnr.unwrap() > 0.0 && error.ssim < 0.5);
+
+        // a transparent pixel counts as white
+        let white = RgbaImage::from_pixel(3, 3, Rgba::from([255, 255, 255, 255]));
+        assert_eq!(ImageError::new(&white, &RgbaImage::new(3, 3)).unwrap().mse, 0.0);
+
+        assert!(matches!(ImageError::new(&gradient, &white), Err(Error::InvalidParameter { .. })));
+    }
+
+    #[test]
+    fn test_stats() {
+        let image = RgbaImage::from_fn(40, 30, |x, _| Rgba::from([if x < 20 { 250 } else { 10 }, 60, 60, 255]));
+        let triangles = vec![
+            Triangle::new(Point { x: 0.0, y: 0.0 }, Point { x: 40.0, y: 0.0 }, Point { x: 0.0, y: 30.0 }),
+            Triangle::new(Point { x: 40.0, y: 0.0 }, Point { x: 40.0, y: 30.0 }, Point { x: 0.0, y: 30.0 }),
+        ];
+        let drawing = Drawer { stroke_width: 0.0, ..Default::default() }.draw(image.clone(), triangles.clone()).unwrap();
+        let stats = Stats::of_drawing(&image, &triangles, &drawing).unwrap();
+        assert_eq!(stats.mesh.triangles, 2);
+        assert!(stats.error.unwrap().mse > 0.0);
+        let rendered = drawing.to_image_with(Fit::Original).unwrap();
+        assert_eq!(Stats::new(&image, &triangles, &rendered).unwrap(), stats);
+
+        let report = stats.to_string();
+        assert!(report.contains("triangles       2 (0 degenerate)") && report.contains("ssim"), "{}", report);
+    }
+}
@@ -0,0 +1,668 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::{AnimationDecoder, Rgba, RgbaImage};
+
+
+/// Temporary directory holding the source image and the outputs of a test.
+struct Workspace {
+    directory: PathBuf,
+}
+
+impl Workspace {
+
+    fn new(name: &str) -> Self {
+        let directory = std::env::temp_dir().join(format!("triangulation_cli_{}", name));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+
+        // red and blue halves with a green square in the middle, the edges give the points
+        let source = RgbaImage::from_fn(64, 48, |x, y| {
+            if (20..44).contains(&x) && (12..36).contains(&y) {
+                Rgba::from([0, 200, 0, 255])
+            } else if x < 32 {
+                Rgba::from([220, 30, 30, 255])
+            } else {
+                Rgba::from([30, 30, 220, 255])
+            }
+        });
+        source.save(directory.join("source.png")).unwrap();
+
+        Self { directory }
+    }
+
+    fn source(&self) -> String {
+        self.path("source.png")
+    }
+
+    fn path(&self, name: &str) -> String {
+        self.directory.join(name).to_string_lossy().into_owned()
+    }
+
+    /// Runs the binary on the source image, writing `name`, with the extra `args`.
+    fn run(&self, name: &str, args: &[&str]) -> Output {
+        let (source, output) = (self.source(), self.path(name));
+        let mut command_args = vec!["--in", source.as_str(), "--out", output.as_str(), "-q"];
+        command_args.extend_from_slice(args);
+        triangulation(&command_args)
+    }
+
+    /// Runs the binary and returns the generated image.
+    fn render(&self, name: &str, args: &[&str]) -> RgbaImage {
+        let output = self.run(name, args);
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        image::open(self.path(name)).unwrap().to_rgba8()
+    }
+
+    /// Runs the binary with --json and returns the summary of the image.
+    fn summary(&self, name: &str, args: &[&str]) -> serde_json::Value {
+        let mut json_args = vec!["--json"];
+        json_args.extend_from_slice(args);
+        let output = self.run(name, &json_args);
+        let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        summary["files"][0].clone()
+    }
+}
+
+impl Drop for Workspace {
+
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.directory);
+    }
+}
+
+fn triangulation(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_triangulation"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn count_pixels(image: &RgbaImage, predicate: impl Fn(&Rgba<u8>) -> bool) -> usize {
+    image.pixels().filter(|pixel| predicate(pixel)).count()
+}
+
+fn is_white(pixel: &Rgba<u8>) -> bool {
+    pixel[0] > 250 && pixel[1] > 250 && pixel[2] > 250 && pixel[3] == 255
+}
+
+fn is_transparent(pixel: &Rgba<u8>) -> bool {
+    pixel[3] == 0
+}
+
+#[test]
+fn test_default_options() {
+    let workspace = Workspace::new("default");
+    let image = workspace.render("default.png", &[]);
+
+    assert_eq!(image.dimensions(), (64, 48));
+    // filled triangles on an opaque background
+    assert_eq!(count_pixels(&image, is_transparent), 0);
+    assert!(count_pixels(&image, |pixel| pixel[0] > 150 && pixel[2] < 100) > 100);
+    assert!(count_pixels(&image, |pixel| pixel[2] > 150 && pixel[0] < 100) > 100);
+}
+
+#[test]
+fn test_only_wireframe() {
+    let workspace = Workspace::new("only_wireframe");
+    let filled = workspace.render("filled.png", &[]);
+    let wireframe = workspace.render("wireframe.png", &["--ow"]);
+
+    assert!(count_pixels(&wireframe, is_white) > count_pixels(&filled, is_white) + 1000);
+}
+
+#[test]
+fn test_stroke_width() {
+    let workspace = Workspace::new("stroke_width");
+    let thin = workspace.render("thin.png", &["--ow", "--sw", "0.5", "--mp", "50"]);
+    let thick = workspace.render("thick.png", &["--ow", "--sw", "3", "--mp", "50"]);
+
+    assert!(count_pixels(&thin, is_white) > count_pixels(&thick, is_white));
+    assert!(!workspace.run("invalid.png", &["--ow", "--sw", "0"]).status.success());
+}
+
+#[test]
+fn test_vertex_markers() {
+    let workspace = Workspace::new("vertex_markers");
+    let is_red = |pixel: &Rgba<u8>| pixel[0] > 200 && pixel[1] < 60 && pixel[2] < 60;
+
+    // only the markers are drawn over the background
+    let points = workspace.render("points.png", &["--op", "--vr", "2", "--vc", "#f00", "--bc", "white", "--mp", "50", "--seed", "1"]);
+    assert!(count_pixels(&points, is_red) > 50);
+    assert_eq!(count_pixels(&points, |pixel| !is_white(pixel) && !is_red(pixel) && pixel[0] < 200), 0);
+
+    // the markers are drawn over the triangles, larger on the strong edges
+    let overlay = workspace.render("overlay.png", &["--vr", "2", "--vc", "#f00", "--mp", "50"]);
+    assert!(count_pixels(&overlay, is_red) > 50);
+    let sized = workspace.render("sized.png", &["--op", "--vr", "2", "--vc", "#f00", "--vertex-sizing", "edge", "--mp", "50", "--seed", "1"]);
+    assert!(count_pixels(&sized, is_red) < count_pixels(&points, is_red));
+
+    assert_eq!(workspace.run("radius.png", &["--op"]).status.code(), Some(3));
+    assert_eq!(workspace.run("wireframe.png", &["--op", "--ow", "--vr", "2"]).status.code(), Some(2));
+}
+
+#[test]
+fn test_with_background() {
+    let workspace = Workspace::new("with_background");
+    let without_background = workspace.render("without.png", &["--ow", "--wb", "false"]);
+    let with_background = workspace.render("with.png", &["--ow", "--wb", "true"]);
+
+    assert!(count_pixels(&without_background, is_transparent) > 1000);
+    assert_eq!(count_pixels(&with_background, is_transparent), 0);
+
+    // a bare --wb is the same as --wb true
+    let bare = workspace.render("bare.png", &["--ow", "--wb"]);
+    assert_eq!(count_pixels(&bare, is_transparent), 0);
+}
+
+#[test]
+fn test_colors() {
+    let workspace = Workspace::new("colors");
+    let image = workspace.render("colors.png", &["--ow", "--bc", "#ff0000", "--sc", "00ff00", "--sw", "2"]);
+
+    assert!(count_pixels(&image, |pixel| *pixel == Rgba::from([255, 0, 0, 255])) > 100);
+    assert!(count_pixels(&image, |pixel| *pixel == Rgba::from([0, 255, 0, 255])) > 100);
+}
+
+#[test]
+fn test_background_alpha() {
+    let workspace = Workspace::new("background_alpha");
+    let image = workspace.render("alpha.png", &["--ow", "--bc", "#0000ff80"]);
+
+    assert!(count_pixels(&image, |pixel| pixel[2] == 255 && (127..=129).contains(&pixel[3])) > 1000);
+    assert!(!workspace.run("invalid.png", &["--bc", "#0000ff8"]).status.success());
+}
+
+#[test]
+fn test_color_syntaxes() {
+    let workspace = Workspace::new("color_syntaxes");
+
+    for (name, color) in [("short", "#f00"), ("rgb", "rgb(255, 0, 0)"), ("hsl", "hsl(0 100% 50%)"), ("named", "Red")] {
+        let image = workspace.render(&format!("{}.png", name), &["--ow", "--bc", color, "--sc", "lime", "--sw", "2"]);
+        assert!(count_pixels(&image, |pixel| *pixel == Rgba::from([255, 0, 0, 255])) > 100, "{}", color);
+        assert!(count_pixels(&image, |pixel| *pixel == Rgba::from([0, 255, 0, 255])) > 100, "{}", color);
+    }
+
+    // invalid colors are rejected by the argument parser, without panicking
+    for color in ["#f", "12", "rgb(1, 2)", "notacolor"] {
+        let output = workspace.run("invalid.png", &["--sc", color]);
+        assert_eq!(output.status.code(), Some(2), "{}", color);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("invalid color"));
+    }
+}
+
+#[test]
+fn test_transparent_source() {
+    let workspace = Workspace::new("transparent_source");
+    let source = workspace.path("transparent.png");
+    // opaque red square on a fully transparent image
+    RgbaImage::from_fn(64, 48, |x, y| {
+        if (16..48).contains(&x) && (12..36).contains(&y) { Rgba::from([220, 30, 30, 255]) } else { Rgba::from([0, 0, 0, 0]) }
+    }).save(&source).unwrap();
+
+    let output = workspace.path("transparent_out.png");
+    let result = triangulation(&["--in", &source, "--out", &output, "--wb", "false", "--sw", "0", "--pr", "0.5", "-q"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+
+    let image = image::open(&output).unwrap().to_rgba8();
+    assert_eq!(image.get_pixel(1, 1)[3], 0);
+    // the triangles over the square keep its color, their antialiased edges only lower the alpha
+    let is_red = |pixel: &Rgba<u8>| pixel[3] > 32 && pixel[0].abs_diff(220) <= 5 && pixel[1].abs_diff(30) <= 5 && pixel[2].abs_diff(30) <= 5;
+    assert_eq!(count_pixels(&image, |pixel| pixel[3] > 32 && !is_red(pixel)), 0);
+    assert!(count_pixels(&image, is_red) > 32 * 24 / 2);
+
+    // the wireframe is drawn over the transparent areas too
+    let wireframe = workspace.path("wireframe_out.png");
+    let result = triangulation(&["--in", &source, "--out", &wireframe, "--wb", "false", "--ow", "--sw", "1", "--pr", "0.5", "-q"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    let image = image::open(&wireframe).unwrap().to_rgba8();
+    let outside = |x: u32, y: u32| !((16..48).contains(&x) && (12..36).contains(&y));
+    assert!(image.enumerate_pixels().filter(|(x, y, pixel)| outside(*x, *y) && pixel[3] > 128).count() > 50);
+}
+
+#[test]
+fn test_stroke_mode() {
+    let workspace = Workspace::new("stroke_mode");
+    let is_dark = |pixel: &Rgba<u8>| pixel[0] < 60 && pixel[1] < 60 && pixel[2] < 60;
+
+    let fixed = workspace.render("fixed.png", &["--sw", "1"]);
+    assert!(count_pixels(&fixed, is_dark) > 100);
+
+    // the strokes take the colors of the triangles, so no dark line is drawn
+    for mode in ["fill", "lighter:0.2", "sampled"] {
+        let image = workspace.render("mode.png", &["--sw", "1", "--stroke-mode", mode]);
+        assert_eq!(count_pixels(&image, is_dark), 0, "{}", mode);
+    }
+    let darker = workspace.render("darker.png", &["--sw", "1", "--stroke-mode", "darker:1"]);
+    assert!(count_pixels(&darker, is_dark) > 100);
+
+    assert_eq!(workspace.run("invalid.png", &["--stroke-mode", "dotted"]).status.code(), Some(2));
+}
+
+#[test]
+fn test_seamless() {
+    let workspace = Workspace::new("seamless");
+    let colors = |image: &RgbaImage| {
+        let mut colors: Vec<_> = image.pixels().map(|pixel| pixel.0).collect();
+        colors.sort();
+        colors.dedup();
+        colors.len()
+    };
+
+    // with a single color for every triangle, any other color comes from the background
+    let single_color = ["--palette-size", "1", "--sw", "0", "--bc", "lime"];
+    assert!(colors(&workspace.render("seams.png", &single_color)) > 1);
+    for backend in ["svg", "raster"] {
+        let image = workspace.render("seamless.png", &[&single_color[..], &["--seamless", "--backend", backend]].concat());
+        assert_eq!(colors(&image), 1, "{}", backend);
+    }
+}
+
+#[test]
+fn test_animation() {
+    let workspace = Workspace::new("animation");
+    let frames = |name: &str| {
+        let file = std::io::BufReader::new(std::fs::File::open(workspace.path(name)).unwrap());
+        let frames = if name.ends_with(".gif") {
+            GifDecoder::new(file).unwrap().into_frames().collect_frames().unwrap()
+        } else {
+            PngDecoder::new(file).unwrap().apng().into_frames().collect_frames().unwrap()
+        };
+        frames.into_iter().map(|frame| frame.into_buffer()).collect::<Vec<_>>()
+    };
+
+    let output = workspace.run("size.gif", &["--animate", "size", "--frames", "5", "--width", "32"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let gif = frames("size.gif");
+    assert_eq!(gif.len(), 5);
+    assert_eq!(gif[0].dimensions(), (32, 24));
+
+    // the background is covered little by little
+    let args = ["--animate", "points", "--frames", "4", "--sw", "0", "--seamless", "--bc", "lime"];
+    let output = workspace.run("points.apng", &args);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let apng = frames("points.apng");
+    assert_eq!(apng.len(), 4);
+    let is_lime = |pixel: &Rgba<u8>| pixel.0 == [0, 255, 0, 255];
+    assert_eq!(count_pixels(&apng[3], is_lime), 0);
+
+    assert_eq!(workspace.run("zero.gif", &["--animate", "size", "--frames", "0"]).status.code(), Some(3));
+    assert_eq!(workspace.run("still.jpeg", &["--animate", "size"]).status.code(), Some(3));
+    assert_eq!(workspace.run("frames.gif", &["--frames", "5"]).status.code(), Some(2));
+}
+
+#[test]
+fn test_morph() {
+    let workspace = Workspace::new("morph");
+    let target = workspace.path("target.png");
+    RgbaImage::from_fn(32, 32, |x, _| if x < 16 { Rgba::from([0, 0, 0, 255]) } else { Rgba::from([255, 255, 0, 255]) })
+        .save(&target)
+        .unwrap();
+
+    let output = workspace.run("morph.gif", &["--morph", &target, "--frames", "4", "--sw", "0"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let file = std::io::BufReader::new(std::fs::File::open(workspace.path("morph.gif")).unwrap());
+    let frames = GifDecoder::new(file).unwrap().into_frames().collect_frames().unwrap();
+    assert_eq!(frames.len(), 4);
+    assert_eq!(frames[0].buffer().dimensions(), (64, 48));
+
+    // the last frame has the colors of the target, stretched to the size of the source
+    let is_source_color = |pixel: &Rgba<u8>| pixel[0] > 150 && pixel[1] < 100 && pixel[2] < 100;
+    assert!(count_pixels(frames[0].buffer(), is_source_color) > 0);
+    assert_eq!(count_pixels(frames[3].buffer(), is_source_color), 0);
+
+    // an image per frame, numbered from 1
+    let output = workspace.run("frame_{frame}.png", &["--morph", &target, "--frames", "3"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    for frame in 1..=3 {
+        let image = image::open(workspace.path(&format!("frame_{}.png", frame))).unwrap();
+        assert_eq!((image.width(), image.height()), (64, 48));
+    }
+    assert!(!Path::new(&workspace.path("frame_4.png")).exists());
+
+    assert_eq!(workspace.run("one.gif", &["--morph", &target, "--frames", "1"]).status.code(), Some(3));
+    assert_eq!(workspace.run("missing.gif", &["--morph", &workspace.path("missing.png")]).status.code(), Some(4));
+    assert_eq!(workspace.run("both.gif", &["--morph", &target, "--animate", "size"]).status.code(), Some(2));
+
+    // the markers of a configuration file are rejected, the frames cannot draw them
+    let settings = workspace.path("markers.toml");
+    std::fs::write(&settings, "[drawer]\nvertex_radius = 2.0\n").unwrap();
+    assert_eq!(workspace.run("markers.gif", &["--morph", &target, "--config", &settings]).status.code(), Some(3));
+}
+
+#[test]
+fn test_sequence() {
+    let workspace = Workspace::new("sequence");
+    let frames = workspace.path("frames");
+    std::fs::create_dir_all(&frames).unwrap();
+    for (index, left) in [10, 10, 16].iter().enumerate() {
+        let frame = RgbaImage::from_fn(64, 48, |x, y| {
+            if (*left..left + 30).contains(&x) && (10..38).contains(&y) {
+                Rgba::from([240, 200, 20, 255])
+            } else {
+                Rgba::from([20, 40, 160, 255])
+            }
+        });
+        frame.save(format!("{}/frame_{:03}.png", frames, index + 1)).unwrap();
+    }
+
+    let out_dir = workspace.path("out");
+    let output = triangulation(&["--in", &frames, "--out-dir", &out_dir, "--sequence", "-q"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let frame = |name: &str| image::open(format!("{}/{}", out_dir, name)).unwrap().to_rgba8();
+
+    // the points are kept on a still frame, and only moved where the edges moved
+    assert_eq!(frame("frame_001.png"), frame("frame_002.png"));
+    assert_ne!(frame("frame_002.png"), frame("frame_003.png"));
+
+    // with a seed the whole sequence is reproducible
+    let seeded = |out_dir: &str| {
+        let output = triangulation(&["--in", &frames, "--out-dir", out_dir, "--sequence", "--seed", "42", "-q"]);
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        image::open(format!("{}/frame_003.png", out_dir)).unwrap().to_rgba8()
+    };
+    assert_eq!(seeded(&workspace.path("first")), seeded(&workspace.path("second")));
+
+    let code = |args: &[&str]| triangulation(args).status.code();
+    assert_eq!(code(&["--in", &frames, "--out-dir", &out_dir, "--sequence", "--jobs", "2"]), Some(2));
+    assert_eq!(code(&["--in", &frames, "--out", &workspace.path("still.png"), "--sequence"]), Some(2));
+    assert_eq!(code(&["--in", &frames, "--out-dir", &out_dir, "--sequence-tolerance", "8"]), Some(2));
+}
+
+#[test]
+fn test_export_mesh() {
+    let workspace = Workspace::new("export_mesh");
+
+    let mesh_path = workspace.path("mesh.json");
+    let summary = workspace.summary("file.png", &["--export-mesh", &mesh_path]);
+    let mesh: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&mesh_path).unwrap()).unwrap();
+    assert_eq!((mesh["width"].as_f64(), mesh["height"].as_f64()), (Some(64.0), Some(48.0)));
+    let triangles = mesh["triangles"].as_array().unwrap();
+    assert_eq!(Some(triangles.len() as u64), summary["triangles"].as_u64());
+    assert_eq!(mesh["colors"].as_array().unwrap().len(), triangles.len());
+    let vertices = mesh["vertices"].as_array().unwrap().len() as u64;
+    assert!(triangles.iter().flat_map(|triangle| triangle.as_array().unwrap()).all(|index| index.as_u64().unwrap() < vertices));
+
+    let output = workspace.run("file.png", &["--export-mesh", &workspace.path("mesh.obj")]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let obj = std::fs::read_to_string(workspace.path("mesh.obj")).unwrap();
+    assert!(obj.starts_with("mtllib mesh.mtl\n"));
+    assert!(std::fs::read_to_string(workspace.path("mesh.mtl")).unwrap().starts_with("newmtl color_"));
+
+    let output = workspace.run("file.png", &["--export-mesh", &workspace.path("mesh.txt"), "--mesh-format", "ply"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(std::fs::read_to_string(workspace.path("mesh.txt")).unwrap().starts_with("ply\n"));
+
+    assert_eq!(workspace.run("file.png", &["--export-mesh", &workspace.path("mesh.stl")]).status.code(), Some(3));
+    assert_eq!(workspace.run("file.gif", &["--export-mesh", &mesh_path, "--animate", "size"]).status.code(), Some(2));
+
+    // a drawing of only the points has no triangle to export
+    assert_eq!(workspace.run("points.png", &["--op", "--vr", "2", "--export-mesh", &mesh_path]).status.code(), Some(2));
+    let settings = workspace.path("points.toml");
+    std::fs::write(&settings, "[drawer]\nonly_points = true\nvertex_radius = 2.0\n").unwrap();
+    assert_eq!(workspace.run("points.png", &["--config", &settings, "--export-mesh", &mesh_path]).status.code(), Some(3));
+}
+
+#[test]
+fn test_import_points_and_mesh() {
+    let workspace = Workspace::new("import");
+    let points = workspace.path("points.csv");
+    std::fs::write(&points, "x,y\n# eyes\n20,12\n44,12\n32,36\n").unwrap();
+
+    let summary = workspace.summary("replace.png", &["--points", &points, "--points-mode", "replace"]);
+    assert_eq!(summary["points"], 3);
+    let summary = workspace.summary("merge.png", &["--points", &points]);
+    assert!(summary["points"].as_u64().unwrap() > 3);
+
+    // the exported mesh renders the same image without detecting the edges again
+    let mesh = workspace.path("mesh.json");
+    let summary = workspace.summary("first.png", &["--export-mesh", &mesh]);
+    let rendered = workspace.summary("second.png", &["--mesh", &mesh]);
+    assert_eq!(rendered["triangles"], summary["triangles"]);
+    assert!(rendered["timings"].as_array().unwrap().iter().all(|timing| timing["stage"] != "sample points"));
+    let image = |name: &str| image::open(workspace.path(name)).unwrap().to_rgba8();
+    assert_eq!(image("first.png"), image("second.png"));
+
+    let outside = workspace.path("outside.json");
+    std::fs::write(&outside, "[[10, 10], [100, 10]]").unwrap();
+    assert_eq!(workspace.run("outside.png", &["--points", &outside]).status.code(), Some(5));
+    assert_eq!(workspace.run("missing.png", &["--points", &workspace.path("missing.csv")]).status.code(), Some(3));
+    assert_eq!(workspace.run("missing.png", &["--mesh", &workspace.path("missing.json")]).status.code(), Some(4));
+    assert_eq!(workspace.run("both.png", &["--mesh", &mesh, "--points", &points]).status.code(), Some(2));
+}
+
+#[test]
+fn test_debug_dir() {
+    let workspace = Workspace::new("debug_dir");
+    let debug_dir = workspace.path("debug");
+
+    let output = workspace.run("file.png", &["--debug-dir", &debug_dir, "--work-size", "32"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stage = |name: &str| image::open(format!("{}/file_{}.png", debug_dir, name)).unwrap().to_rgba8();
+    for name in ["blur", "edge", "mask"] {
+        assert_eq!(stage(name).dimensions(), (32, 24), "{}", name);
+    }
+    for name in ["points", "wireframe"] {
+        assert_eq!(stage(name).dimensions(), (64, 48), "{}", name);
+    }
+    assert!(count_pixels(&stage("points"), |pixel| pixel.0 == [255, 0, 0, 255]) > 0);
+    assert!(count_pixels(&stage("mask"), is_white) > 0);
+
+    // only the stages that run are saved
+    let mesh = workspace.path("mesh.json");
+    workspace.run("first.png", &["--export-mesh", &mesh]);
+    let output = workspace.run("second.png", &["--mesh", &mesh, "--debug-dir", &debug_dir]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(Path::new(&format!("{}/second_wireframe.png", debug_dir)).exists());
+    assert!(!Path::new(&format!("{}/second_edge.png", debug_dir)).exists());
+}
+
+#[test]
+fn test_stats() {
+    let workspace = Workspace::new("stats");
+
+    let summary = workspace.summary("file.png", &["--stats", "--mp", "200", "--seed", "1"]);
+    let stats = &summary["stats"];
+    assert_eq!(stats["mesh"]["triangles"], summary["triangles"]);
+    assert!(stats["mesh"]["points"].as_u64().unwrap() > 3);
+    let histogram: u64 = stats["mesh"]["min_angle_histogram"].as_array().unwrap().iter().map(|count| count.as_u64().unwrap()).sum();
+    assert_eq!(histogram + stats["mesh"]["degenerate"].as_u64().unwrap(), summary["triangles"].as_u64().unwrap());
+    assert!(stats["mesh"]["min_angle"]["max"].as_f64().unwrap() <= 60.0 + 1e-9);
+    let ssim = stats["error"]["ssim"].as_f64().unwrap();
+    assert!(stats["error"]["psnr"].as_f64().unwrap() > 0.0 && ssim > 0.0 && ssim <= 1.0);
+
+    // more triangles follow the source image more closely
+    let coarse = workspace.summary("coarse.png", &["--stats", "--mp", "20", "--seed", "1"]);
+    assert!(coarse["stats"]["error"]["mse"].as_f64().unwrap() > stats["error"]["mse"].as_f64().unwrap());
+
+    let (source, output) = (workspace.source(), workspace.path("text.png"));
+    let text = triangulation(&["--in", &source, "--out", &output, "--stats"]);
+    let stdout = String::from_utf8_lossy(&text.stdout);
+    assert!(stdout.contains("min angle") && stdout.contains("psnr"), "{}", stdout);
+
+    // the stats are printed with --quiet, and measure the resized image that is written
+    let quiet = workspace.run("quiet.png", &["--stats", "--width", "32"]);
+    let stdout = String::from_utf8_lossy(&quiet.stdout);
+    assert!(stdout.contains("min angle") && !stdout.contains("done"), "{}", stdout);
+    assert_eq!(image::image_dimensions(workspace.path("quiet.png")).unwrap(), (32, 24));
+    assert!(workspace.summary("plain.png", &[]).get("stats").is_none());
+}
+
+#[test]
+fn test_palette() {
+    let workspace = Workspace::new("palette");
+    let palette = workspace.path("brand.gpl");
+    std::fs::write(&palette, "GIMP Palette\nName: Brand\n#\n255 255 0 Yellow\n0 255 255 Cyan\n").unwrap();
+
+    let image = workspace.render("file.png", &["--palette", &palette, "--sw", "0", "--wb", "false"]);
+    let is_palette_color = |pixel: &Rgba<u8>| matches!((pixel[0], pixel[1], pixel[2]), (255, 255, 0) | (0, 255, 255));
+    assert_eq!(count_pixels(&image, |pixel| pixel[3] == 255 && !is_palette_color(pixel)), 0);
+
+    for method in ["median-cut", "k-means"] {
+        let image = workspace.render("auto.png", &["--palette-size", "2", "--palette-method", method, "--sw", "0", "--wb", "false"]);
+        let mut colors: Vec<_> = image.pixels().filter(|pixel| pixel[3] == 255).map(|pixel| pixel.0).collect();
+        colors.sort();
+        colors.dedup();
+        assert!(colors.len() <= 2, "{}: {:?}", method, colors);
+    }
+
+    // the palette is extracted from the adjusted colors, so they stay sepia
+    let sepia = workspace.render("sepia.png", &["--sepia", "1", "--palette-size", "3", "--sw", "0", "--wb", "false"]);
+    let is_sepia = |pixel: &Rgba<u8>| pixel[0] >= pixel[1] && pixel[1] >= pixel[2];
+    assert_eq!(count_pixels(&sepia, |pixel| pixel[3] == 255 && !is_sepia(pixel)), 0);
+
+    assert_eq!(workspace.run("missing.png", &["--palette", &workspace.path("missing.gpl")]).status.code(), Some(3));
+    assert_eq!(workspace.run("empty.png", &["--palette-size", "0"]).status.code(), Some(3));
+    assert_eq!(workspace.run("method.png", &["--palette-method", "k-means"]).status.code(), Some(2));
+}
+
+#[test]
+fn test_color_adjustments() {
+    let workspace = Workspace::new("color_adjustments");
+    let is_gray = |pixel: &Rgba<u8>| pixel[0].abs_diff(pixel[1]) <= 1 && pixel[1].abs_diff(pixel[2]) <= 1;
+
+    let desaturated = workspace.render("desaturated.png", &["--saturation", "0", "--sw", "0"]);
+    assert!(desaturated.pixels().all(is_gray));
+
+    let duotone = workspace.render("duotone.png", &["--duotone", "black", "white", "--sw", "0"]);
+    assert!(duotone.pixels().all(is_gray));
+
+    // without background, the antialiased edges between the triangles only lower the alpha
+    let posterized = workspace.render("posterized.png", &["--posterize", "2", "--hue", "-30", "--sw", "0", "--wb", "false"]);
+    let posterized_count = count_pixels(&posterized, |pixel| pixel.0[..3].iter().all(|channel| [0, 255].contains(channel)));
+    assert!(posterized_count > 64 * 48 * 9 / 10);
+
+    assert_eq!(workspace.run("invalid.png", &["--sepia", "2"]).status.code(), Some(3));
+}
+
+#[test]
+fn test_grayscale() {
+    let workspace = Workspace::new("grayscale");
+    let image = workspace.render("grayscale.png", &["--gr", "--sw", "0"]);
+
+    assert!(image.pixels().all(|pixel| pixel[0] == pixel[1] && pixel[1] == pixel[2]));
+}
+
+#[test]
+fn test_point_options() {
+    let workspace = Workspace::new("point_options");
+
+    let limited = workspace.summary("limited.png", &["--mp", "10"]);
+    assert!(limited["points"].as_u64().unwrap() <= 10);
+
+    let few = workspace.summary("few.png", &["--pr", "0.05", "--mp", "100000"]);
+    let many = workspace.summary("many.png", &["--pr", "0.5", "--mp", "100000"]);
+    assert!(few["points"].as_u64().unwrap() < many["points"].as_u64().unwrap());
+
+    let narrow = workspace.summary("narrow.png", &["--sf", "1", "--pr", "1", "--mp", "100000"]);
+    let wide = workspace.summary("wide.png", &["--sf", "6", "--pr", "1", "--mp", "100000"]);
+    assert!(narrow["points"].as_u64().unwrap() < wide["points"].as_u64().unwrap());
+
+    let sharp = workspace.summary("sharp.png", &["--bf", "0", "--pt", "100", "--pr", "1", "--mp", "100000"]);
+    let blurred = workspace.summary("blurred.png", &["--bf", "4", "--pt", "100", "--pr", "1", "--mp", "100000"]);
+    assert_ne!(sharp["points"], blurred["points"]);
+
+    // an uniform image has no edge, so no point
+    let uniform = workspace.path("uniform.png");
+    RgbaImage::from_pixel(16, 16, Rgba::from([128, 128, 128, 255])).save(&uniform).unwrap();
+    let output = triangulation(&["--in", &uniform, "--out", &workspace.path("uniform_out.png")]);
+    assert_eq!(output.status.code(), Some(5));
+}
+
+#[test]
+fn test_output_size() {
+    let workspace = Workspace::new("output_size");
+
+    assert_eq!(workspace.render("width.png", &["--width", "32"]).dimensions(), (32, 24));
+    assert_eq!(workspace.render("height.png", &["--height", "96"]).dimensions(), (128, 96));
+    assert_eq!(workspace.render("size.png", &["--width", "32", "--height", "32"]).dimensions(), (32, 24));
+    assert_eq!(workspace.render("stretch.png", &["--width", "32", "--height", "32", "--stretch"]).dimensions(), (32, 32));
+    assert_eq!(workspace.render("scale.png", &["--scale", "2"]).dimensions(), (128, 96));
+}
+
+#[test]
+fn test_output_format() {
+    let workspace = Workspace::new("output_format");
+
+    assert!(workspace.run("guessed.bmp", &[]).status.success());
+    assert!(workspace.run("explicit.img", &["--format", "bmp"]).status.success());
+    for name in ["guessed.bmp", "explicit.img"] {
+        let bytes = std::fs::read(workspace.path(name)).unwrap();
+        assert_eq!(&bytes[0..2], b"BM");
+    }
+}
+
+#[test]
+fn test_config_and_preset() {
+    let workspace = Workspace::new("config");
+    let config = workspace.path("style.toml");
+
+    let output = triangulation(&["--preset", "wireframe", "--mp", "42", "--dump-config", &config, "-q"]);
+    assert!(output.status.success());
+    let content = std::fs::read_to_string(&config).unwrap();
+    assert!(content.contains("max_points = 42"));
+    assert!(content.contains("only_wireframe = true"));
+
+    let summary = workspace.summary("config.png", &["--config", &config]);
+    assert!(summary["points"].as_u64().unwrap() <= 42);
+
+    // the boolean settings of a file can be turned off from the command line
+    let settings = workspace.path("settings.toml");
+    std::fs::write(&settings, "[triangulation]\ngrayscale = true\n\n[drawer]\nonly_wireframe = true\nstroke_width = 1.0\n").unwrap();
+    let is_colored = |pixel: &Rgba<u8>| pixel[0] != pixel[1] || pixel[1] != pixel[2];
+    let from_file = workspace.render("from_file.png", &["--config", &settings, "--bc", "white"]);
+    assert_eq!(count_pixels(&from_file, is_colored), 0);
+    assert!(count_pixels(&from_file, is_white) > 1000);
+    let overridden = workspace.render("overridden.png", &["--config", &settings, "--bc", "white", "--no-gr", "--no-ow"]);
+    assert!(count_pixels(&overridden, is_colored) > 1000);
+    assert!(count_pixels(&overridden, is_white) < 100);
+
+    // the last of a flag and its negation wins
+    let last = workspace.render("last.png", &["--config", &settings, "--bc", "white", "--no-ow", "--ow"]);
+    assert!(count_pixels(&last, is_white) > 1000);
+
+    // the presets are listed as a json array with --json
+    let text = triangulation(&["--list-presets"]);
+    assert!(String::from_utf8_lossy(&text.stdout).contains("wireframe"));
+    let json = triangulation(&["--list-presets", "--json"]);
+    assert!(json.status.success());
+    let presets: serde_json::Value = serde_json::from_slice(&json.stdout).unwrap();
+    let presets = presets.as_array().unwrap();
+    assert!(presets.iter().any(|preset| preset["name"] == "wireframe" && preset["description"].is_string()));
+}
+
+#[test]
+fn test_batch() {
+    let workspace = Workspace::new("batch");
+    let out_dir = workspace.path("out");
+    std::fs::write(workspace.path("broken.png"), b"not an image").unwrap();
+
+    let output = triangulation(&["--in", &workspace.source(), "--out-dir", &out_dir, "--name", "{stem}_lowpoly.png", "-q"]);
+    assert!(output.status.success());
+    assert!(Path::new(&out_dir).join("source_lowpoly.png").exists());
+
+    let output = triangulation(&["--in", &workspace.path(""), "--out-dir", &out_dir, "-q"]);
+    assert_eq!(output.status.code(), Some(7));
+    assert!(Path::new(&out_dir).join("source.png").exists());
+
+    // the default names take the extension of --format, a --name with another extension is rejected
+    let jpeg_dir = workspace.path("jpeg");
+    let output = triangulation(&["--in", &workspace.source(), "--out-dir", &jpeg_dir, "--format", "jpeg", "-q"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let bytes = std::fs::read(Path::new(&jpeg_dir).join("source.jpg")).unwrap();
+    assert_eq!(bytes[..3], [0xff, 0xd8, 0xff]);
+    let output = triangulation(&["--in", &workspace.source(), "--out-dir", &jpeg_dir, "--format", "jpeg", "--name", "{stem}.png", "-q"]);
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn test_exit_codes() {
+    let workspace = Workspace::new("exit_codes");
+
+    assert_eq!(workspace.run("ok.png", &[]).status.code(), Some(0));
+    assert_eq!(workspace.run("invalid.png", &["--pr", "2"]).status.code(), Some(3));
+    assert_eq!(triangulation(&["--in", &workspace.path("missing.png"), "--out", &workspace.path("out.png")]).status.code(), Some(4));
+    assert_eq!(workspace.run("unknown.png", &["--unknown"]).status.code(), Some(2));
+}